@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+  Move(i32, i32),
+  PickUp,
+  UseItem(usize, Option<(i32, i32)>),
+  Descend,
+  CloseDoors,
+  LevelUp(Stat),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stat {
+  Hp,
+  Power,
+  Defense,
+}
@@ -1,4 +1,12 @@
-#[derive(Clone, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Ai {
   Basic,
+  // Shoots from up to `range` tiles away, and backs off from a player who
+  // comes closer than `keep_distance`
+  Ranged {
+    range: i32,
+    keep_distance: i32,
+  },
 }
@@ -1,47 +1,110 @@
-use ::tcod::colors::*;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
 use ::tcod::console::*;
-use ::tcod::input::{self, Event, Key};
-use ::tcod::map::{FovAlgorithm, Map as FovMap};
-
-use crate::constants::*;
-use crate::game::Game;
-use crate::map::Map;
-use crate::object::Object;
-use crate::render::{render_game, render_menu};
+use ::tcod::input::{Key, KeyCode};
+
+use roguelike::constants::*;
+use roguelike::action::{Action, Stat};
+use roguelike::content::Content;
+use roguelike::game::Game;
+use roguelike::replay::Replay;
+use roguelike::object::Object;
+use roguelike::messages::Category;
+use roguelike::dijkstra::Goal;
+use roguelike::save::{delete_save, load_game, save_exists, save_game};
+use roguelike::item::Targeting;
+
+use crate::options::Options;
+use crate::render::{names_at, render_game, render_menu, render_message_log, Overlay, LOG_PAGE};
+use crate::targeting::select_target;
 use crate::tcod::Tcod;
 
-static FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic;
-static FOV_LIGHT_WALLS: bool = true;
-static TORCH_RADIUS: i32 = 10;
-
 static INVENTORY_WIDTH: i32 = 50;
+static LEVEL_SCREEN_WIDTH: i32 = 40;
 
 static LIMIT_FPS: i32 = 20;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum PlayerAction {
-  TookTurn,
-  DidntTakeTurn,
-  Exit,
-}
+// How long fired projectiles stay on screen
+static PROJECTILE_DELAY: u64 = 120;
 
 fn get_names_under_mouse(tcod: &Tcod, game: &Game) -> String {
-  let (x, y) = (tcod.mouse.cx as i32, tcod.mouse.cy as i32);
+  names_at(game, tcod.mouse.cx as i32, tcod.mouse.cy as i32)
+}
+
+fn look(tcod: &mut Tcod, game: &Game) {
+  let prompt = "Look at what? Press Enter to examine, or Escape to cancel.";
+  let target = select_target(tcod, game, Targeting::Tile, None, prompt);
+
+  let (x, y) = match target {
+    Some(target) => target.pos(game),
+    None => return,
+  };
 
-  let names = game.objects
+  let descriptions: Vec<String> = game.objects
     .iter()
-    .filter(|obj| obj.pos() == (x, y) && tcod.fov.is_in_fov(obj.x, obj.y))
-    .map(|obj| obj.name.clone())
-    .collect::<Vec<_>>();
+    .filter(|obj| obj.pos() == (x, y))
+    .map(|obj| match obj.fighter {
+      Some((fighter, _)) if obj.alive => format!("{} ({} HP)", obj.name, fighter.hp),
+      _ => obj.name.clone(),
+    })
+    .collect();
+
+  let text = if descriptions.is_empty() {
+    "You see nothing of interest.".to_string()
+  } else {
+    format!("You see: {}", descriptions.join(", "))
+  };
 
-  names.join(", ")
+  render_menu(tcod, &text, &[] as &[&str], INVENTORY_WIDTH);
+  tcod.root.wait_for_keypress(true);
+}
+
+fn next_filter(filter: Option<Category>) -> Option<Category> {
+  match filter {
+    None => Some(Category::Combat),
+    Some(Category::Combat) => Some(Category::Items),
+    Some(Category::Items) => Some(Category::System),
+    Some(Category::System) => None,
+  }
+}
+
+fn show_message_log(tcod: &mut Tcod, game: &Game) {
+  let mut filter = None;
+  let mut scroll = 0;
+
+  while !tcod.root.window_closed() {
+    // Scrolling stops once the oldest page is on screen
+    let last = render_message_log(tcod, game, filter, scroll);
+    let key = tcod.root.wait_for_keypress(true);
+
+    match key.code {
+      KeyCode::Escape => return,
+      KeyCode::PageUp => scroll = (scroll + LOG_PAGE).min(last),
+      KeyCode::PageDown => scroll = scroll.saturating_sub(LOG_PAGE),
+      KeyCode::Up => scroll = (scroll + 1).min(last),
+      KeyCode::Down => scroll = scroll.saturating_sub(1),
+      KeyCode::Tab => {
+        filter = next_filter(filter);
+        scroll = 0;
+      },
+      _ => {}
+    }
+  }
 }
 
 fn show_inventory(tcod: &mut Tcod, inventory: &[Object], header: &str) -> Option<usize> {
   let options = if inventory.is_empty() {
     vec!["Inventory is empty.".into()]
   } else {
-    inventory.iter().map(|item| item.name.clone()).collect()
+    inventory
+      .iter()
+      .map(|item| match item.equipment {
+        Some(equipment) if equipment.equipped => format!("{} (on {})", item.name, equipment.slot),
+        _ => item.name.clone(),
+      })
+      .collect()
   };
 
   render_menu(tcod, header, &options, INVENTORY_WIDTH);
@@ -57,66 +120,144 @@ fn show_inventory(tcod: &mut Tcod, inventory: &[Object], header: &str) -> Option
   None
 }
 
-#[allow(clippy::ptr_arg)]
-fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> PlayerAction {
-  use ::tcod::input::KeyCode::*;
+fn level_up_menu(tcod: &mut Tcod, game: &Game) -> Stat {
+  let fighter = game.objects[PLAYER].fighter.unwrap().0;
+
+  let options = [
+    format!("Constitution (+20 HP, from {})", fighter.base_max_hp),
+    format!("Strength (+1 attack, from {})", fighter.base_power),
+    format!("Agility (+1 defense, from {})", fighter.base_defense),
+  ];
 
-  let player_alive = game.objects[PLAYER].alive;
+  while !tcod.root.window_closed() {
+    render_menu(tcod, "Level up! Choose a stat to raise:\n", &options, LEVEL_SCREEN_WIDTH);
+    let key = tcod.root.wait_for_keypress(true);
+
+    match key.printable.to_ascii_lowercase() {
+      'a' => return Stat::Hp,
+      'b' => return Stat::Power,
+      'c' => return Stat::Defense,
+      _ => {}
+    }
+  }
+
+  // The window was closed without a choice, so the game can still be saved
+  Stat::Hp
+}
+
+// The overlay only shows maps the game has cached, so it asks for the one it
+// shows to be brought up to date first
+fn refresh_overlay(tcod: &Tcod, game: &mut Game) {
+  if let Some(overlay) = tcod.overlay {
+    let (goal, flee) = overlay.map();
+    game.refresh_dijkstra_map(goal, flee);
+  }
+}
+
+fn show_projectiles(tcod: &mut Tcod, game: &mut Game) {
+  if game.projectiles.is_empty() { return; }
+
+  refresh_overlay(tcod, game);
+  let names_under_mouse = get_names_under_mouse(tcod, game);
+  render_game(tcod, game, names_under_mouse, None);
+  thread::sleep(Duration::from_millis(PROJECTILE_DELAY));
+
+  game.projectiles.clear();
+}
+
+fn next_overlay(overlay: Option<Overlay>) -> Option<Overlay> {
+  match overlay {
+    None => Some(Overlay::Chase(Goal::Player)),
+    Some(Overlay::Chase(Goal::Player)) => Some(Overlay::Flee(Goal::Player)),
+    Some(Overlay::Flee(Goal::Player)) => Some(Overlay::Chase(Goal::Items)),
+    Some(Overlay::Chase(Goal::Items)) => Some(Overlay::Chase(Goal::Stairs)),
+    _ => None,
+  }
+}
+
+fn perform(game: &mut Game, replay: &mut Option<Replay>, action: Action) -> Result<(), String> {
+  game.perform(action)?;
+
+  if let Some(replay) = replay.as_mut() {
+    replay.record(action);
+  }
+
+  Ok(())
+}
+
+fn take_turn(tcod: &mut Tcod, game: &mut Game, replay: &mut Option<Replay>, action: Action) -> Result<(), String> {
+  perform(game, replay, action)?;
+  show_projectiles(tcod, game);
+
+  while game.can_level_up() {
+    let stat = level_up_menu(tcod, game);
+    perform(game, replay, Action::LevelUp(stat))?;
+  }
+
+  Ok(())
+}
 
-  match (tcod.key, tcod.key.text(), player_alive) {
-    ( Key { code: Enter, alt: true, .. }, _, _ ) => {
+fn handle_keys(tcod: &mut Tcod, game: &Game) -> Option<Action> {
+  use ::tcod::input::KeyCode::*;
+
+  match (tcod.key, tcod.key.text()) {
+    ( Key { code: Enter, alt: true, .. }, _ ) => {
       let fullscreen = tcod.root.is_fullscreen();
       tcod.root.set_fullscreen(!fullscreen);
 
-      return PlayerAction::DidntTakeTurn
+      None
     },
-    ( Key { code: Escape, .. }, _, _ ) => return PlayerAction::Exit,
-    ( Key { code: Up, .. }, _, true ) => game.player_move_or_attack(0, -1),
-    ( Key { code: Down, .. }, _, true ) => game.player_move_or_attack(0, 1),
-    ( Key { code: Left, .. }, _, true ) => game.player_move_or_attack(-1, 0),
-    ( Key { code: Right, .. }, _, true ) => game.player_move_or_attack(1, 0),
-    ( Key { code: Text, .. }, "g", true ) => {
-      let item_id = game.objects
-        .iter()
-        .position(|obj| obj.pos() == game.objects[PLAYER].pos() && obj.item.is_some());
-
-      if let Some(item_id) = item_id {
-        game.pick_item_up(item_id);
-      }
+    ( Key { code: F2, .. }, _ ) => {
+      tcod.overlay = next_overlay(tcod.overlay);
+
+      None
+    },
+    ( Key { code: Up, .. }, _ ) => Some(Action::Move(0, -1)),
+    ( Key { code: Down, .. }, _ ) => Some(Action::Move(0, 1)),
+    ( Key { code: Left, .. }, _ ) => Some(Action::Move(-1, 0)),
+    ( Key { code: Right, .. }, _ ) => Some(Action::Move(1, 0)),
+    ( Key { code: Text, .. }, "g" ) => Some(Action::PickUp),
+    ( Key { code: Text, .. }, ">" ) => Some(Action::Descend),
+    ( Key { code: Text, .. }, "c" ) => Some(Action::CloseDoors),
+    ( Key { code: Text, .. }, "l" ) => {
+      look(tcod, game);
+
+      None
+    },
+    ( Key { code: Text, .. }, "m" ) => {
+      show_message_log(tcod, game);
 
-      return PlayerAction::DidntTakeTurn
+      None
     },
-    ( Key { code: Text, .. }, "i", true ) => {
-      if let Some(inventory_id) = show_inventory(
+    ( Key { code: Text, .. }, "i" ) => {
+      let inventory_id = show_inventory(
         tcod,
         &game.inventory,
         "Press the key listed next to an item to use it, or any other key to cancel.\n"
-      ) {
-        game.use_item(inventory_id);
-      }
+      )?;
 
-      return PlayerAction::DidntTakeTurn
-    },
-    _ => return PlayerAction::DidntTakeTurn,
-  }
+      let targeting = game.inventory
+        .get(inventory_id)
+        .and_then(|obj| obj.item.as_ref())
+        .and_then(|item| item.targeting().map(|targeting| (targeting, item.target_range())));
 
-  PlayerAction::TookTurn
-}
+      let target = match targeting {
+        Some((targeting, max_range)) => {
+          let prompt = "Choose a target with the mouse or arrow keys, or press Escape to cancel.";
+          let target = select_target(tcod, game, targeting, max_range, prompt)?;
 
-fn set_fov_map(fov: &mut FovMap, map: &Map) {
-  for y in 0..MAP_HEIGHT {
-    for x in 0..MAP_WIDTH {
-      fov.set(
-        x,
-        y,
-        !map.tiles[x as usize][y as usize].block_sight,
-        !map.tiles[x as usize][y as usize].blocked,
-      );
-    }
+          Some(target.pos(game))
+        }
+        None => None,
+      };
+
+      Some(Action::UseItem(inventory_id, target))
+    },
+    _ => None,
   }
 }
 
-pub fn play() {
+fn init_tcod() -> Tcod {
   let root = Root::initializer()
     .font("arial10x10.png", FontLayout::Tcod)
     .font_type(FontType::Greyscale)
@@ -124,53 +265,123 @@ pub fn play() {
     .title("Rust/libtcod tutorial")
     .init();
 
-  let mut tcod = Tcod {
+  let tcod = Tcod {
     root,
     con: Offscreen::new(MAP_WIDTH, MAP_HEIGHT),
     panel: Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT),
-    fov: FovMap::new(MAP_WIDTH, MAP_HEIGHT),
     key: Default::default(),
     mouse: Default::default(),
+    overlay: None,
   };
 
   ::tcod::system::set_fps(LIMIT_FPS);
 
-  let mut game = Game::new();
-  set_fov_map(&mut tcod.fov, &game.map);
+  tcod
+}
+
+pub fn play(options: &Options, content: Rc<Content>) {
+  let new_game = || {
+    let seed = options.seed.unwrap_or_else(rand::random);
+    Game::new(seed, options.generator.unwrap_or_default(), content.clone())
+  };
+
+  let game = if !options.starts_new_game() && save_exists() {
+    load_game(content.clone()).or_else(|err| {
+      eprintln!("Could not load the saved game: {}", err);
+      new_game()
+    })
+  } else {
+    new_game()
+  };
+
+  let mut game = match game {
+    Ok(game) => game,
+    Err(err) => {
+      eprintln!("{}", err);
+      return;
+    }
+  };
 
-  let mut previous_player_position = (-1, -1);
+  let mut tcod = init_tcod();
 
-  game.messages.add(
-    "Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.",
-    RED,
-  );
+  let mut replay = options.record.as_ref().map(|_| Replay::new(game.seed, game.generator, &game.content));
 
   while !tcod.root.window_closed() {
-    let fov_recompute = previous_player_position != (game.objects[PLAYER].pos());
+    tcod.poll_input();
 
-    if fov_recompute {
-      let player = &game.objects[PLAYER];
-      tcod.fov.compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
+    let names_under_mouse = get_names_under_mouse(&tcod, &game);
+
+    refresh_overlay(&tcod, &mut game);
+    render_game(&mut tcod, &game, names_under_mouse, None);
+
+    if tcod.key.code == KeyCode::Escape { break; }
+
+    if let Some(action) = handle_keys(&mut tcod, &game) {
+      if let Err(err) = take_turn(&mut tcod, &mut game, &mut replay, action) {
+        eprintln!("{}", err);
+        return;
+      }
     }
+  }
+
+  if let (Some(replay), Some(path)) = (replay.as_mut(), &options.record) {
+    replay.finish(&game);
+
+    if let Err(err) = replay.save(path) {
+      eprintln!("Could not save the replay: {}", err);
+    }
+  }
+
+  let result = if game.objects[PLAYER].alive { save_game(&game) } else { delete_save() };
 
-    match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
-      Some((_, Event::Mouse(m))) => tcod.mouse = m,
-      Some((_, Event::Key(k))) => tcod.key = k,
-      _ => tcod.key = Default::default(),
+  if let Err(err) = result {
+    eprintln!("Could not save the game: {}", err);
+  }
+}
+
+pub fn watch_replay(replay: &Replay, speed: u64, content: Rc<Content>) {
+  if let Err(err) = replay.check_content(&content) {
+    eprintln!("{}", err);
+    return;
+  }
+
+  let mut game = match Game::new(replay.seed, replay.generator, content) {
+    Ok(game) => game,
+    Err(err) => {
+      eprintln!("{}", err);
+      return;
     }
+  };
+
+  let mut tcod = init_tcod();
+  let mut actions = replay.actions.iter();
+
+  while !tcod.root.window_closed() {
+    tcod.poll_input();
 
     let names_under_mouse = get_names_under_mouse(&tcod, &game);
+    render_game(&mut tcod, &game, names_under_mouse, None);
 
-    render_game(&mut tcod, &mut game, names_under_mouse);
+    if tcod.key.code == KeyCode::Escape { break; }
 
-    let player = &mut game.objects[0];
-    previous_player_position = (player.x, player.y);
+    match actions.next() {
+      Some(&action) => {
+        if let Err(err) = game.perform(action) {
+          eprintln!("{}", err);
+          break;
+        }
 
-    let action = handle_keys(&mut tcod, &mut game);
-    if action == PlayerAction::Exit { break; }
+        show_projectiles(&mut tcod, &mut game);
+        thread::sleep(Duration::from_millis(speed));
+      }
+      None => {
+        if let Err(err) = replay.check_final_state(&game) {
+          eprintln!("{}", err);
+        }
 
-    if game.objects[PLAYER].alive && action == PlayerAction::TookTurn {
-      game.update_objects(&tcod.fov);
+        tcod.root.wait_for_keypress(true);
+        break;
+      }
     }
   }
 }
@@ -0,0 +1,127 @@
+use std::cmp;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::map::Map;
+use crate::prefab::Prefab;
+use crate::rect::Rect;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BspConfig {
+  pub min_leaf_size: i32,
+  pub max_leaf_size: i32,
+  // The smallest share of a partition either side of a split can take
+  pub split_ratio: f32,
+}
+
+impl Default for BspConfig {
+  fn default() -> Self {
+    BspConfig { min_leaf_size: 8, max_leaf_size: 20, split_ratio: 0.35 }
+  }
+}
+
+impl BspConfig {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.min_leaf_size < 4 {
+      return Err("The minimum leaf size must be at least 4".into());
+    }
+
+    if self.max_leaf_size < self.min_leaf_size * 2 {
+      return Err("The maximum leaf size must be at least twice the minimum leaf size".into());
+    }
+
+    if !(0.1..=0.5).contains(&self.split_ratio) {
+      return Err("The split ratio must be between 0.1 and 0.5".into());
+    }
+
+    Ok(())
+  }
+}
+
+fn split_point<R: Rng>(length: i32, config: &BspConfig, rng: &mut R) -> Option<i32> {
+  let min = cmp::max(config.min_leaf_size, (length as f32 * config.split_ratio) as i32);
+  let max = length - min;
+
+  if max < min { return None; }
+
+  Some(rng.gen_range(min, max + 1))
+}
+
+fn carve_leaf<R: Rng>(map: &mut Map, leaf: Rect, prefabs: &[Prefab], rng: &mut R) -> Vec<Rect> {
+  let (leaf_w, leaf_h) = (leaf.x2 - leaf.x1, leaf.y2 - leaf.y1);
+
+  let w = rng.gen_range(cmp::min(ROOM_MIN_SIZE, leaf_w), leaf_w + 1);
+  let h = rng.gen_range(cmp::min(ROOM_MIN_SIZE, leaf_h), leaf_h + 1);
+  let x = rng.gen_range(leaf.x1, leaf.x2 - w + 1);
+  let y = rng.gen_range(leaf.y1, leaf.y2 - h + 1);
+
+  let room = Rect::new(x, y, w, h);
+  map.carve_room(room, prefabs, rng);
+
+  vec![room]
+}
+
+fn connect<R: Rng>(map: &mut Map, first: &[Rect], second: &[Rect], rng: &mut R) {
+  let (x1, y1) = first[rng.gen_range(0, first.len())].center();
+  let (x2, y2) = second[rng.gen_range(0, second.len())].center();
+
+  if rng.gen() {
+    map.create_h_tunnel(x1, x2, y1);
+    map.create_v_tunnel(y1, y2, x2);
+  } else {
+    map.create_v_tunnel(y1, y2, x1);
+    map.create_h_tunnel(x1, x2, y2);
+  }
+}
+
+// Splits the partition in two until it is small enough, carves a room into
+// each leaf, and then joins every pair of siblings on the way back up.
+// Returns the rooms inside this partition.
+fn partition<R: Rng>(map: &mut Map, area: Rect, config: &BspConfig, prefabs: &[Prefab], rng: &mut R) -> Vec<Rect> {
+  let (w, h) = (area.x2 - area.x1, area.y2 - area.y1);
+
+  let split_vertically = if w as f32 > h as f32 * 1.25 {
+    true
+  } else if h as f32 > w as f32 * 1.25 {
+    false
+  } else {
+    rng.gen()
+  };
+
+  let must_split = w > config.max_leaf_size || h > config.max_leaf_size;
+  if !must_split && rng.gen_weighted_bool(4) {
+    return carve_leaf(map, area, prefabs, rng);
+  }
+
+  let (first, second) = if split_vertically {
+    match split_point(w, config, rng) {
+      Some(split) => (
+        Rect::new(area.x1, area.y1, split, h),
+        Rect::new(area.x1 + split, area.y1, w - split, h),
+      ),
+      None => return carve_leaf(map, area, prefabs, rng),
+    }
+  } else {
+    match split_point(h, config, rng) {
+      Some(split) => (
+        Rect::new(area.x1, area.y1, w, split),
+        Rect::new(area.x1, area.y1 + split, w, h - split),
+      ),
+      None => return carve_leaf(map, area, prefabs, rng),
+    }
+  };
+
+  let mut rooms = partition(map, first, config, prefabs, rng);
+  let second_rooms = partition(map, second, config, prefabs, rng);
+
+  connect(map, &rooms, &second_rooms, rng);
+
+  rooms.extend(second_rooms);
+  rooms
+}
+
+pub fn generate<R: Rng>(map: &mut Map, config: &BspConfig, prefabs: &[Prefab], rng: &mut R) {
+  let area = Rect::new(0, 0, MAP_WIDTH - 1, MAP_HEIGHT - 1);
+  map.rooms = partition(map, area, config, prefabs, rng);
+}
@@ -0,0 +1,103 @@
+use rand::Rng;
+
+use crate::constants::*;
+use crate::map::Map;
+use crate::tile::Tile;
+
+static FILL_CHANCE: f32 = 0.45;
+static SMOOTHING_PASSES: u32 = 5;
+// Regions smaller than this are filled in rather than tunnelled into the cave
+static MIN_REGION_SIZE: usize = 16;
+// Retry if the largest region covers less of the map than this
+static MIN_CAVE_SHARE: f32 = 0.3;
+static MAX_ATTEMPTS: u32 = 10;
+
+fn is_border(x: i32, y: i32) -> bool {
+  x == 0 || y == 0 || x == MAP_WIDTH - 1 || y == MAP_HEIGHT - 1
+}
+
+fn random_fill<R: Rng>(map: &mut Map, rng: &mut R) {
+  for x in 0..MAP_WIDTH {
+    for y in 0..MAP_HEIGHT {
+      map.tiles[x as usize][y as usize] = if is_border(x, y) || rng.gen::<f32>() < FILL_CHANCE {
+        Tile::wall()
+      } else {
+        Tile::empty()
+      };
+    }
+  }
+}
+
+fn wall_neighbours(map: &Map, x: i32, y: i32) -> usize {
+  let mut count = 0;
+
+  for dx in -1..=1 {
+    for dy in -1..=1 {
+      if dx == 0 && dy == 0 { continue; }
+
+      let (nx, ny) = (x + dx, y + dy);
+      let outside = nx < 0 || ny < 0 || nx >= MAP_WIDTH || ny >= MAP_HEIGHT;
+
+      if outside || map.tiles[nx as usize][ny as usize].blocked() {
+        count += 1;
+      }
+    }
+  }
+
+  count
+}
+
+// A tile becomes a wall when most of the tiles around it are walls, which
+// clumps the noise into open caverns
+fn smooth(map: &mut Map) {
+  let mut walls = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+
+  for x in 0..MAP_WIDTH {
+    for y in 0..MAP_HEIGHT {
+      let is_wall = map.tiles[x as usize][y as usize].blocked();
+      let neighbours = wall_neighbours(map, x, y);
+
+      walls[x as usize][y as usize] = is_border(x, y) || neighbours >= 5 || (is_wall && neighbours >= 4);
+    }
+  }
+
+  for x in 0..MAP_WIDTH {
+    for y in 0..MAP_HEIGHT {
+      map.tiles[x as usize][y as usize] =
+        if walls[x as usize][y as usize] { Tile::wall() } else { Tile::empty() };
+    }
+  }
+}
+
+pub fn generate<R: Rng>(map: &mut Map, rng: &mut R) {
+  let min_cave_size = ((MAP_WIDTH * MAP_HEIGHT) as f32 * MIN_CAVE_SHARE) as usize;
+
+  for attempt in 1..=MAX_ATTEMPTS {
+    random_fill(map, rng);
+
+    for _ in 0..SMOOTHING_PASSES {
+      smooth(map);
+    }
+
+    let mut regions = map.regions();
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+
+    let cave_size = regions.first().map_or(0, |region| region.len());
+    if cave_size < min_cave_size && attempt < MAX_ATTEMPTS { continue; }
+
+    let mut cave = if regions.is_empty() { return; } else { regions.remove(0) };
+
+    for region in regions {
+      if region.len() < MIN_REGION_SIZE {
+        for &(x, y) in &region {
+          map.tiles[x as usize][y as usize] = Tile::wall();
+        }
+      } else {
+        map.tunnel_between(&region, &cave, rng);
+        cave.extend(region);
+      }
+    }
+
+    return;
+  }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+}
+
+pub const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+pub const RED: Color = Color { r: 255, g: 0, b: 0 };
+pub const DARK_RED: Color = Color { r: 191, g: 0, b: 0 };
+pub const ORANGE: Color = Color { r: 255, g: 127, b: 0 };
+pub const YELLOW: Color = Color { r: 255, g: 255, b: 0 };
+pub const LIGHT_YELLOW: Color = Color { r: 255, g: 255, b: 63 };
+pub const GREEN: Color = Color { r: 0, g: 255, b: 0 };
+pub const LIGHT_GREEN: Color = Color { r: 63, g: 255, b: 63 };
+pub const LIGHT_BLUE: Color = Color { r: 63, g: 63, b: 255 };
+pub const LIGHT_VIOLET: Color = Color { r: 159, g: 63, b: 255 };
+pub const LIGHT_GREY: Color = Color { r: 159, g: 159, b: 159 };
+pub const GOLD: Color = Color { r: 255, g: 191, b: 0 };
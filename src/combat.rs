@@ -0,0 +1,108 @@
+use rand::Rng;
+
+use crate::dice::Dice;
+
+// A d20 roll plus accuracy minus evasion must reach this to hit
+static HIT_TARGET: i32 = 6;
+static CRITICAL_ROLL: i32 = 20;
+static FUMBLE_ROLL: i32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Attack {
+  pub accuracy: i32,
+  pub damage: Dice,
+  // Added to every damage roll
+  pub power: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Defense {
+  pub evasion: i32,
+  // Subtracted from every damage roll
+  pub armor: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+  // A hit can still do no damage once armor is taken off
+  Hit(i32),
+  Critical(i32),
+  Miss,
+  Fumble,
+}
+
+// Takes the generator as a parameter so callers can pin the rolls
+pub fn resolve<R: Rng>(rng: &mut R, attack: &Attack, defense: &Defense) -> Outcome {
+  let roll = rng.gen_range(1, 21);
+
+  if roll == FUMBLE_ROLL {
+    return Outcome::Fumble;
+  }
+
+  // A critical hit rolls its damage dice twice and goes straight through armor
+  if roll == CRITICAL_ROLL {
+    let damage = attack.damage.roll(rng) + attack.damage.roll(rng) + attack.power;
+    return Outcome::Critical(damage.max(1));
+  }
+
+  if roll + attack.accuracy - defense.evasion < HIT_TARGET {
+    return Outcome::Miss;
+  }
+
+  let damage = attack.damage.roll(rng) + attack.power - defense.armor;
+
+  Outcome::Hit(damage.max(0))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rng::{seeded_rng, GameRng};
+
+  // A generator whose first d20 comes up as `roll`
+  fn rolling(roll: i32) -> GameRng {
+    (0..)
+      .map(seeded_rng)
+      .find(|rng| rng.clone().gen_range(1, 21) == roll)
+      .unwrap()
+  }
+
+  fn attack(accuracy: i32, damage: &str, power: i32) -> Attack {
+    Attack { accuracy, damage: damage.parse().unwrap(), power }
+  }
+
+  #[test]
+  fn hits_take_off_armor() {
+    let outcome = resolve(&mut rolling(10), &attack(0, "5", 1), &Defense { evasion: 0, armor: 2 });
+
+    assert_eq!(outcome, Outcome::Hit(4));
+  }
+
+  #[test]
+  fn armor_can_stop_all_the_damage() {
+    let outcome = resolve(&mut rolling(10), &attack(0, "1", 0), &Defense { evasion: 0, armor: 10 });
+
+    assert_eq!(outcome, Outcome::Hit(0));
+  }
+
+  #[test]
+  fn evasion_makes_attacks_miss() {
+    let outcome = resolve(&mut rolling(10), &attack(0, "1d6", 0), &Defense { evasion: 10, armor: 0 });
+
+    assert_eq!(outcome, Outcome::Miss);
+  }
+
+  #[test]
+  fn criticals_roll_twice_and_ignore_armor() {
+    let outcome = resolve(&mut rolling(CRITICAL_ROLL), &attack(0, "3", 1), &Defense { evasion: 100, armor: 100 });
+
+    assert_eq!(outcome, Outcome::Critical(7));
+  }
+
+  #[test]
+  fn fumbles_miss_whatever_the_accuracy() {
+    let outcome = resolve(&mut rolling(FUMBLE_ROLL), &attack(100, "1d6", 0), &Defense { evasion: 0, armor: 0 });
+
+    assert_eq!(outcome, Outcome::Fumble);
+  }
+}
@@ -7,9 +7,17 @@ pub static MAP_HEIGHT: i32 = 43;
 pub static PANEL_HEIGHT: i32 = 7;
 
 pub static MAX_ROOMS: i32 = 30;
-pub static MAX_ROOM_MONSTERS: i32 = 3;
-pub static MAX_ROOM_ITEMS: i32 = 2;
+pub static ROOM_MAX_SIZE: i32 = 10;
+pub static ROOM_MIN_SIZE: i32 = 6;
+// (minimum depth, value) pairs
+pub static MAX_ROOM_MONSTERS: &[(u32, i32)] = &[(1, 3), (4, 4), (6, 5)];
+pub static MAX_ROOM_ITEMS: &[(u32, i32)] = &[(1, 2), (4, 3)];
 
 pub static PLAYER: usize = 0;
 
-pub static HEAL_AMOUNT: i32 = 4;
+// Actors gain their speed in energy every turn, and can act once they have
+// ACTION_ENERGY. Most actions spend that much.
+pub static NORMAL_SPEED: i32 = 100;
+pub static ACTION_ENERGY: i32 = 100;
+
+pub static LAVA_DAMAGE: i32 = 8;
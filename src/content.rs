@@ -0,0 +1,137 @@
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::Path;
+
+use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::Serialize;
+
+use crate::loot::{self, Loot, ITEM_FILE, LOOT_FILE};
+use crate::monster::{self, MonsterTemplate, MONSTER_FILE};
+use crate::prefab::{self, Prefab, PREFAB_DIR};
+
+// Everything read from the data files. It's handed to whatever generates
+// levels, so a game without it loaded is empty rather than silently different.
+#[derive(Default, Serialize)]
+pub struct Content {
+  pub prefabs: Vec<Prefab>,
+  pub monsters: Vec<MonsterTemplate>,
+  pub loot: Loot,
+}
+
+impl Content {
+  pub fn load() -> Result<Content, String> {
+    let prefabs = prefab::load_prefabs(Path::new(PREFAB_DIR))?;
+
+    let monsters = monster::load_monsters(Path::new(MONSTER_FILE))?;
+    let loot = loot::load_loot(Path::new(ITEM_FILE), Path::new(LOOT_FILE))?;
+
+    Ok(Content { prefabs, monsters, loot })
+  }
+}
+
+// Deserializes a list one entry at a time, counting the entries as they
+// succeed so an error can be traced back to the entry it came from
+struct Counted<'a, T> {
+  parsed: &'a Cell<usize>,
+  entry: PhantomData<T>,
+}
+
+impl<'de, 'a, T: DeserializeOwned> DeserializeSeed<'de> for Counted<'a, T> {
+  type Value = Vec<T>;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+    deserializer.deserialize_seq(self)
+  }
+}
+
+impl<'de, 'a, T: DeserializeOwned> Visitor<'de> for Counted<'a, T> {
+  type Value = Vec<T>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a list")
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+    let mut values = vec![];
+
+    while let Some(value) = seq.next_element()? {
+      values.push(value);
+      self.parsed.set(values.len());
+    }
+
+    Ok(values)
+  }
+}
+
+// The entries of a content file, along with what's needed to point at one of
+// them in an error message
+pub struct Entries<T> {
+  pub values: Vec<T>,
+  kind: &'static str,
+  names: Vec<Option<String>>,
+}
+
+impl<T> Entries<T> {
+  pub fn describe(&self, index: usize) -> String {
+    describe(self.kind, index, &self.names)
+  }
+}
+
+fn describe(kind: &str, index: usize, names: &[Option<String>]) -> String {
+  match names.get(index) {
+    Some(Some(name)) => format!("{} {} (\"{}\")", kind, index + 1, name),
+    _ => format!("{} {}", kind, index + 1),
+  }
+}
+
+// The names of the entries, as far as they can be read without knowing
+// whether the rest of each entry is valid
+fn entry_names(text: &str) -> Vec<Option<String>> {
+  let entries: Vec<ron::Value> = ron::from_str(text).unwrap_or_default();
+
+  entries
+    .iter()
+    .map(|entry| match entry {
+      ron::Value::Map(map) => map.iter().find_map(|(key, value)| match (key, value) {
+        (ron::Value::String(key), ron::Value::String(name)) if key == "name" => Some(name.clone()),
+        _ => None,
+      }),
+      _ => None,
+    })
+    .collect()
+}
+
+// Checks a table of (minimum depth, value) pairs, as read by from_depth
+pub fn check_depth_table(table: &[(u32, i32)]) -> Result<(), String> {
+  if table.iter().any(|&(_, weight)| weight < 0) {
+    return Err("weights can't be negative".into());
+  }
+
+  if !table.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+    return Err("weights must be listed in order of increasing depth".into());
+  }
+
+  Ok(())
+}
+
+// Parses a RON list of entries. Errors name the entry they were found in,
+// along with its line and column.
+pub fn parse_list<T: DeserializeOwned>(text: &str, kind: &'static str) -> Result<Entries<T>, String> {
+  let parsed = Cell::new(0);
+  let names = entry_names(text);
+
+  let values = ron::Options::default()
+    .from_str_seed(text, Counted { parsed: &parsed, entry: PhantomData })
+    .map_err(|err| {
+      format!(
+        "{}, line {} column {}: {}",
+        describe(kind, parsed.get(), &names),
+        err.position.line,
+        err.position.col,
+        err.code,
+      )
+    })?;
+
+  Ok(Entries { values, kind, names })
+}
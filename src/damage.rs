@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use crate::color::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DamageType {
+  #[default]
+  Physical,
+  Fire,
+  Cold,
+  Lightning,
+  Poison,
+}
+
+impl DamageType {
+  // What a resistance message says was shrugged off or resisted
+  pub fn source(&self) -> &'static str {
+    match self {
+      DamageType::Physical => "the blow",
+      DamageType::Fire => "the flames",
+      DamageType::Cold => "the cold",
+      DamageType::Lightning => "the shock",
+      DamageType::Poison => "the poison",
+    }
+  }
+
+  // Colour of projectiles that deal this type of damage
+  pub fn color(&self) -> Color {
+    match self {
+      DamageType::Physical => LIGHT_GREY,
+      DamageType::Fire => ORANGE,
+      DamageType::Cold => LIGHT_BLUE,
+      DamageType::Lightning => LIGHT_YELLOW,
+      DamageType::Poison => GREEN,
+    }
+  }
+}
+
+// Percentages of each type of damage that are ignored. 100 is immunity, and
+// below 0 is a vulnerability that adds to the damage taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Resistances {
+  pub physical: i32,
+  pub fire: i32,
+  pub cold: i32,
+  pub lightning: i32,
+  pub poison: i32,
+}
+
+impl Resistances {
+  pub fn get(&self, kind: DamageType) -> i32 {
+    match kind {
+      DamageType::Physical => self.physical,
+      DamageType::Fire => self.fire,
+      DamageType::Cold => self.cold,
+      DamageType::Lightning => self.lightning,
+      DamageType::Poison => self.poison,
+    }
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    let all = [self.physical, self.fire, self.cold, self.lightning, self.poison];
+
+    if all.iter().any(|&resistance| resistance > 100) {
+      return Err("resistances can't be over 100".into());
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,204 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+static MAX_DICE: u32 = 100;
+static MAX_SIDES: u32 = 1000;
+// Keeps even the largest roll well inside an i32. Plain numbers count as a
+// bonus with no dice.
+static MAX_BONUS: i32 = 1000;
+
+fn check_bonus(bonus: i32) -> Result<i32, String> {
+  if (-MAX_BONUS..=MAX_BONUS).contains(&bonus) {
+    Ok(bonus)
+  } else {
+    Err(format!("{} is more than {} away from 0", bonus, MAX_BONUS))
+  }
+}
+
+// A roll such as "2d6+1": `count` dice with `sides` sides each, plus a fixed
+// bonus. A plain number is a roll with no dice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dice {
+  pub count: u32,
+  pub sides: u32,
+  pub bonus: i32,
+}
+
+impl Dice {
+  pub fn flat(value: i32) -> Self {
+    Dice { count: 0, sides: 0, bonus: value }
+  }
+
+  pub fn roll<R: Rng>(&self, rng: &mut R) -> i32 {
+    let rolled: i32 = (0..self.count).map(|_| rng.gen_range(1, self.sides as i32 + 1)).sum();
+
+    rolled + self.bonus
+  }
+
+  // The lowest the roll can come out, with every die showing 1
+  pub fn min(&self) -> i32 {
+    self.count as i32 + self.bonus
+  }
+}
+
+impl FromStr for Dice {
+  type Err = String;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let text = text.trim();
+    let invalid = || format!("\"{}\" is not a dice roll like \"1d6+2\"", text);
+
+    let (count, rest) = match text.split_once('d') {
+      Some(parts) => parts,
+      None => return check_bonus(text.parse().map_err(|_| invalid())?).map(Dice::flat),
+    };
+
+    let count = if count.is_empty() { 1 } else { count.parse().map_err(|_| invalid())? };
+
+    let (sides, bonus) = match rest.find(['+', '-']) {
+      Some(index) => {
+        let (sides, bonus) = rest.split_at(index);
+        (sides, bonus.parse().map_err(|_| invalid())?)
+      }
+      None => (rest, 0),
+    };
+
+    let sides = sides.parse().map_err(|_| invalid())?;
+
+    if count == 0 || sides == 0 {
+      return Err(format!("\"{}\" needs at least one die with at least one side", text));
+    }
+
+    if count > MAX_DICE {
+      return Err(format!("\"{}\" rolls more than {} dice", text, MAX_DICE));
+    }
+
+    if sides > MAX_SIDES {
+      return Err(format!("\"{}\" rolls dice with more than {} sides", text, MAX_SIDES));
+    }
+
+    let bonus = check_bonus(bonus).map_err(|err| format!("\"{}\": {}", text, err))?;
+
+    Ok(Dice { count, sides, bonus })
+  }
+}
+
+impl fmt::Display for Dice {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.count == 0 {
+      return write!(f, "{}", self.bonus);
+    }
+
+    write!(f, "{}d{}", self.count, self.sides)?;
+
+    match self.bonus {
+      0 => Ok(()),
+      bonus if bonus > 0 => write!(f, "+{}", bonus),
+      bonus => write!(f, "{}", bonus),
+    }
+  }
+}
+
+impl Serialize for Dice {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+struct DiceVisitor;
+
+impl<'de> Visitor<'de> for DiceVisitor {
+  type Value = Dice;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a number or a dice roll like \"1d6+2\"")
+  }
+
+  fn visit_str<E: de::Error>(self, value: &str) -> Result<Dice, E> {
+    value.parse().map_err(E::custom)
+  }
+
+  fn visit_i64<E: de::Error>(self, value: i64) -> Result<Dice, E> {
+    let value = i32::try_from(value).map_err(|_| E::custom("the number is too large"))?;
+    check_bonus(value).map(Dice::flat).map_err(E::custom)
+  }
+
+  fn visit_u64<E: de::Error>(self, value: u64) -> Result<Dice, E> {
+    let value = i32::try_from(value).map_err(|_| E::custom("the number is too large"))?;
+    check_bonus(value).map(Dice::flat).map_err(E::custom)
+  }
+}
+
+impl<'de> Deserialize<'de> for Dice {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(DiceVisitor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rng::seeded_rng;
+
+  fn parse(text: &str) -> Result<Dice, String> {
+    text.parse()
+  }
+
+  #[test]
+  fn parses_rolls() {
+    assert_eq!(parse("1d6+2"), Ok(Dice { count: 1, sides: 6, bonus: 2 }));
+    assert_eq!(parse("2d4-1"), Ok(Dice { count: 2, sides: 4, bonus: -1 }));
+    assert_eq!(parse("d6"), Ok(Dice { count: 1, sides: 6, bonus: 0 }));
+  }
+
+  #[test]
+  fn parses_plain_numbers() {
+    assert_eq!(parse("3"), Ok(Dice::flat(3)));
+    assert_eq!(parse("-2"), Ok(Dice::flat(-2)));
+  }
+
+  #[test]
+  fn rejects_bad_rolls() {
+    assert!(parse("1d6-").is_err());
+    assert!(parse("1d6+-2").is_err());
+    assert!(parse("0d6").is_err());
+    assert!(parse("1d0").is_err());
+    assert!(parse("d").is_err());
+  }
+
+  #[test]
+  fn rejects_too_many_dice_or_sides() {
+    assert!(parse(&format!("{}d6", MAX_DICE)).is_ok());
+    assert!(parse(&format!("{}d6", MAX_DICE + 1)).is_err());
+    assert!(parse(&format!("1d{}", MAX_SIDES + 1)).is_err());
+    assert!(parse("1d4294967295").is_err());
+    assert!(parse(&format!("1d4+{}", MAX_BONUS)).is_ok());
+    assert!(parse(&format!("1d4-{}", MAX_BONUS + 1)).is_err());
+    assert!(parse("1d4+2147483647").is_err());
+    assert!(parse("1d4-2147483648").is_err());
+    assert!(parse("2147483647").is_err());
+  }
+
+  #[test]
+  fn rolls_stay_in_range() {
+    let dice = Dice { count: 3, sides: 6, bonus: -2 };
+    let mut rng = seeded_rng(1);
+
+    for _ in 0..1000 {
+      let roll = dice.roll(&mut rng);
+      assert!(roll >= dice.min() && roll <= 16);
+    }
+  }
+
+  #[test]
+  fn displays_as_parsed() {
+    for &text in &["1d6+2", "2d4-1", "1d8", "3", "-2"] {
+      assert_eq!(parse(text).unwrap().to_string(), text);
+    }
+  }
+}
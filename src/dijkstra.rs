@@ -0,0 +1,125 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::constants::*;
+use crate::map::{tile_index, tile_position, Map};
+
+static FLEE_FACTOR: f32 = -1.2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Goal {
+  Player,
+  Items,
+  Stairs,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Entry {
+  value: f32,
+  index: usize,
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+  fn cmp(&self, other: &Entry) -> Ordering {
+    other.value.total_cmp(&self.value)
+  }
+}
+
+impl PartialOrd for Entry {
+  fn partial_cmp(&self, other: &Entry) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// Each tile holds its distance in steps to the nearest goal, so any number of
+// monsters can find their way by stepping to their lowest neighbour
+pub struct DijkstraMap {
+  pub goals: Vec<(i32, i32)>,
+  pub revision: u32,
+  values: Vec<f32>,
+}
+
+impl DijkstraMap {
+  pub fn new(map: &Map, goals: &[(i32, i32)], revision: u32) -> Self {
+    let seeds = goals.iter().map(|&(x, y)| (tile_index(x, y), 0.0)).collect();
+
+    DijkstraMap { goals: goals.to_vec(), revision, values: scan(map, seeds) }
+  }
+
+  // Scaling by a negative factor and rescanning makes the goals into peaks.
+  // Rolling downhill then leads away from them, but towards open space rather
+  // than into the nearest dead end.
+  pub fn inverted(&self, map: &Map) -> Self {
+    let seeds = self.values
+      .iter()
+      .enumerate()
+      .filter(|(_, value)| value.is_finite())
+      .map(|(index, &value)| (index, value * FLEE_FACTOR))
+      .collect();
+
+    DijkstraMap { goals: self.goals.clone(), revision: self.revision, values: scan(map, seeds) }
+  }
+
+  pub fn value(&self, x: i32, y: i32) -> f32 {
+    self.values[tile_index(x, y)]
+  }
+
+  pub fn downhill<F: Fn(i32, i32) -> bool>(&self, x: i32, y: i32, is_blocked: F) -> Option<(i32, i32)> {
+    let mut best = None;
+    let mut best_value = self.value(x, y);
+
+    for (nx, ny) in neighbours(x, y) {
+      let value = self.value(nx, ny);
+
+      if value < best_value && !is_blocked(nx, ny) {
+        best = Some((nx, ny));
+        best_value = value;
+      }
+    }
+
+    best
+  }
+}
+
+fn neighbours(x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> {
+  (-1..=1)
+    .flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+    .filter(move |&(nx, ny)| {
+      (nx, ny) != (x, y) && nx >= 0 && ny >= 0 && nx < MAP_WIDTH && ny < MAP_HEIGHT
+    })
+}
+
+fn scan(map: &Map, seeds: Vec<(usize, f32)>) -> Vec<f32> {
+  let mut values = vec![f32::INFINITY; (MAP_WIDTH * MAP_HEIGHT) as usize];
+  let mut open = BinaryHeap::new();
+
+  for (index, value) in seeds {
+    if value < values[index] {
+      values[index] = value;
+      open.push(Entry { value, index });
+    }
+  }
+
+  while let Some(Entry { value, index }) = open.pop() {
+    if value > values[index] { continue; }
+
+    let (x, y) = tile_position(index);
+
+    for (nx, ny) in neighbours(x, y) {
+      let tile = &map.tiles[nx as usize][ny as usize];
+      if !tile.is_walkable() { continue; }
+
+      let next = tile_index(nx, ny);
+      let next_value = value + tile.kind.path_cost() as f32;
+
+      if next_value < values[next] {
+        values[next] = next_value;
+        open.push(Entry { value: next_value, index: next });
+      }
+    }
+  }
+
+  values
+}
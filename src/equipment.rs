@@ -0,0 +1,35 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::damage::Resistances;
+use crate::dice::Dice;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Slot {
+  RightHand,
+  LeftHand,
+  Body,
+}
+
+impl fmt::Display for Slot {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Slot::RightHand => write!(f, "right hand"),
+      Slot::LeftHand => write!(f, "left hand"),
+      Slot::Body => write!(f, "body"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equipment {
+  pub slot: Slot,
+  pub equipped: bool,
+  // Weapons replace the wielder's own damage roll
+  pub damage: Option<Dice>,
+  pub power_bonus: i32,
+  pub defense_bonus: i32,
+  pub max_hp_bonus: i32,
+  pub resistances: Resistances,
+}
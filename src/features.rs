@@ -0,0 +1,57 @@
+use rand::Rng;
+use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+
+use crate::constants::*;
+use crate::map::Map;
+use crate::tile::{Tile, TileKind};
+
+static MIN_FEATURES: u32 = 3;
+static MAX_FEATURES: u32 = 8;
+static MIN_FEATURE_SIZE: u32 = 4;
+static MAX_FEATURE_SIZE: u32 = 24;
+
+// Scatters patches of water, lava, rubble, grass and trees over the floor.
+// Each patch grows from a random floor tile by wandering through open space,
+// and the middle of every spawn area is left clear for the player and stairs.
+pub fn place<R: Rng>(map: &mut Map, rng: &mut R) {
+  let floor: Vec<(i32, i32)> = (0..MAP_WIDTH)
+    .flat_map(|x| (0..MAP_HEIGHT).map(move |y| (x, y)))
+    .filter(|&(x, y)| map.tiles[x as usize][y as usize].kind == TileKind::Floor)
+    .collect();
+
+  if floor.is_empty() { return; }
+
+  let reserved: Vec<_> = map.spawn_areas().iter().map(|area| area.center).collect();
+
+  let feature_chances = &mut [
+    Weighted { weight: 3, item: TileKind::Water },
+    Weighted { weight: 1, item: TileKind::Lava },
+    Weighted { weight: 3, item: TileKind::Rubble },
+    Weighted { weight: 4, item: TileKind::Grass },
+    Weighted { weight: 2, item: TileKind::Tree },
+  ];
+  let choice = WeightedChoice::new(feature_chances);
+
+  for _ in 0..rng.gen_range(MIN_FEATURES, MAX_FEATURES + 1) {
+    let kind = choice.ind_sample(rng);
+    let (mut x, mut y) = floor[rng.gen_range(0, floor.len())];
+
+    for _ in 0..rng.gen_range(MIN_FEATURE_SIZE, MAX_FEATURE_SIZE + 1) {
+      let tile = &mut map.tiles[x as usize][y as usize];
+
+      if tile.kind == TileKind::Floor && !reserved.contains(&(x, y)) {
+        *tile = Tile::new(kind);
+      }
+
+      let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0, 4)];
+      let (nx, ny) = (x + dx, y + dy);
+
+      if nx > 0 && ny > 0 && nx < MAP_WIDTH - 1 && ny < MAP_HEIGHT - 1
+        && map.tiles[nx as usize][ny as usize].kind != TileKind::Wall
+      {
+        x = nx;
+        y = ny;
+      }
+    }
+  }
+}
@@ -1,9 +1,20 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use crate::damage::{DamageType, Resistances};
+use crate::dice::Dice;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Fighter {
-  pub max_hp: i32,
+  pub base_max_hp: i32,
   pub hp: i32,
-  pub defense: i32,
-  pub power: i32,
+  pub base_defense: i32,
+  pub base_power: i32,
+  pub damage: Dice,
+  pub damage_type: DamageType,
+  pub accuracy: i32,
+  pub evasion: i32,
+  pub resistances: Resistances,
+  pub xp: i32,
 }
 
 impl Fighter {
@@ -0,0 +1,117 @@
+use std::cmp;
+
+use crate::line::Line;
+
+pub struct Fov {
+  width: i32,
+  height: i32,
+  transparent: Vec<bool>,
+  visible: Vec<bool>,
+}
+
+impl Fov {
+  pub fn new(width: i32, height: i32) -> Self {
+    let size = (width * height) as usize;
+
+    Fov { width, height, transparent: vec![false; size], visible: vec![false; size] }
+  }
+
+  fn index(&self, x: i32, y: i32) -> usize {
+    (x + y * self.width) as usize
+  }
+
+  fn in_bounds(&self, x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && x < self.width && y < self.height
+  }
+
+  pub fn set(&mut self, x: i32, y: i32, transparent: bool) {
+    let index = self.index(x, y);
+    self.transparent[index] = transparent;
+  }
+
+  pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+    self.in_bounds(x, y) && self.visible[self.index(x, y)]
+  }
+
+  // Ray casting towards every cell on the edge of the radius' bounding box,
+  // in the same manner as libtcod's FOV_BASIC
+  pub fn compute(&mut self, x: i32, y: i32, radius: i32, light_walls: bool) {
+    for cell in self.visible.iter_mut() { *cell = false; }
+
+    if !self.in_bounds(x, y) { return; }
+
+    let index = self.index(x, y);
+    self.visible[index] = true;
+
+    let (x_min, y_min, x_max, y_max) = if radius > 0 {
+      (
+        cmp::max(0, x - radius),
+        cmp::max(0, y - radius),
+        cmp::min(self.width, x + radius + 1),
+        cmp::min(self.height, y + radius + 1),
+      )
+    } else {
+      (0, 0, self.width, self.height)
+    };
+
+    let r2 = radius * radius;
+
+    for edge_x in x_min..x_max {
+      self.cast_ray((x, y), (edge_x, y_min), r2, light_walls);
+      self.cast_ray((x, y), (edge_x, y_max - 1), r2, light_walls);
+    }
+
+    for edge_y in (y_min + 1)..(y_max - 1) {
+      self.cast_ray((x, y), (x_min, edge_y), r2, light_walls);
+      self.cast_ray((x, y), (x_max - 1, edge_y), r2, light_walls);
+    }
+
+    if light_walls {
+      self.light_walls(x_min, y_min, x, y, -1, -1);
+      self.light_walls(x, y_min, x_max - 1, y, 1, -1);
+      self.light_walls(x_min, y, x, y_max - 1, -1, 1);
+      self.light_walls(x, y, x_max - 1, y_max - 1, 1, 1);
+    }
+  }
+
+  fn cast_ray(&mut self, origin: (i32, i32), dest: (i32, i32), r2: i32, light_walls: bool) {
+    for (x, y) in Line::new(origin, dest) {
+      if !self.in_bounds(x, y) { return; }
+
+      if r2 > 0 && (x - origin.0).pow(2) + (y - origin.1).pow(2) > r2 { return; }
+
+      let index = self.index(x, y);
+
+      if !self.transparent[index] {
+        if light_walls { self.visible[index] = true; }
+        return;
+      }
+
+      self.visible[index] = true;
+    }
+  }
+
+  // Rays can slip past the corners of walls that should be lit, so light any
+  // wall next to a visible floor cell on the side facing away from the origin
+  fn light_walls(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, dx: i32, dy: i32) {
+    for x in x0..=x1 {
+      for y in y0..=y1 {
+        let index = self.index(x, y);
+        if !self.visible[index] || !self.transparent[index] { continue; }
+
+        let (x2, y2) = (x + dx, y + dy);
+        let x_in_range = x2 >= x0 && x2 <= x1;
+        let y_in_range = y2 >= y0 && y2 <= y1;
+
+        if x_in_range { self.light_wall(x2, y); }
+        if y_in_range { self.light_wall(x, y2); }
+        if x_in_range && y_in_range { self.light_wall(x2, y2); }
+      }
+    }
+  }
+
+  fn light_wall(&mut self, x: i32, y: i32) {
+    let index = self.index(x, y);
+    if !self.transparent[index] { self.visible[index] = true; }
+  }
+}
@@ -1,172 +1,425 @@
+use std::rc::Rc;
+
 use rand::Rng;
-use ::tcod::map::{Map as FovMap};
-use ::tcod::{colors, colors::*};
+use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use serde::{Deserialize, Serialize};
 
 use crate::constants::*;
-use crate::messages::Messages;
+use crate::color::*;
+use crate::fov::Fov;
+use crate::action::{Action, Stat};
+use crate::rng::{seeded_rng, GameRng};
+use crate::generator::Generator;
+use crate::content::Content;
+use crate::prefab::Marker;
+use crate::loot::{FLOOR_TABLE, TREASURE_TABLE};
+use crate::path::{find_path, MAX_PATH_LENGTH};
+use crate::dijkstra::{DijkstraMap, Goal};
+use crate::messages::{Category, Messages};
 use crate::object::Object;
 use crate::fighter::Fighter;
 use crate::death::Death;
 use crate::ai::Ai;
 use crate::map::Map;
+use crate::tile::Door;
 use crate::item::Item;
+use crate::equipment::Equipment;
+use crate::status::Effect;
+use crate::dice::Dice;
+use crate::damage::{DamageType, Resistances};
+use crate::combat::{self, Attack, Defense, Outcome};
+use crate::line::Line;
 
-fn mut_two<T>(first: usize, second: usize, items: &mut [T]) -> (&mut T, &mut T) {
-  assert!(first != second);
+static LEVEL_UP_BASE: i32 = 200;
+static LEVEL_UP_FACTOR: i32 = 150;
 
-  let split_at_index = std::cmp::max(first, second);
-  let (first_slice, second_slice) = items.split_at_mut(split_at_index);
+// How far away, in steps, monsters out of sight can still track the player
+static HEARING_RANGE: f32 = 12.0;
 
-  if first < second {
-    (&mut first_slice[first], &mut second_slice[0])
-  } else {
-    (&mut second_slice[0], &mut first_slice[second])
-  }
-}
+static FOV_LIGHT_WALLS: bool = true;
+static TORCH_RADIUS: i32 = 10;
+static BLIND_RADIUS: i32 = 1;
 
+// Picking up or using an item takes half as long as other actions
+static ITEM_ENERGY: i32 = 50;
 
 fn player_death(player: &mut Object, messages: &mut Messages) {
-  messages.add("You died!", RED);
+  messages.add(Category::Combat, "You died!", RED);
 
   player.char = '%';
   player.color = DARK_RED;
 }
 
 fn monster_death(monster: &mut Object, messages: &mut Messages) {
-  messages.add(format!("{} died!", monster.name), ORANGE);
+  messages.add(Category::Combat, format!("{} died!", monster.name), ORANGE);
 
   monster.char = '%';
   monster.color = DARK_RED;
   monster.blocks = false;
   monster.fighter = None;
   monster.ai = None;
+  monster.statuses.clear();
   monster.name = format!("remains of {}", monster.name);
 }
 
+fn empty_fov() -> Fov {
+  Fov::new(MAP_WIDTH, MAP_HEIGHT)
+}
+
+fn from_depth(table: &[(u32, i32)], depth: u32) -> i32 {
+  table
+    .iter()
+    .rev()
+    .find(|&&(min_depth, _)| depth >= min_depth)
+    .map_or(0, |&(_, value)| value)
+}
+
+pub struct Projectile {
+  pub path: Vec<(i32, i32)>,
+  pub color: Color,
+}
+
 enum UseResult {
   UsedUp,
+  Kept,
   Cancelled,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Game {
   pub map: Map,
   pub objects: Vec<Object>,
   pub inventory: Vec<Object>,
   pub messages: Messages,
+  #[serde(skip, default = "empty_fov")]
+  pub fov: Fov,
+  pub seed: u32,
+  pub generator: Generator,
+  pub turns: u32,
+  pub depth: u32,
+  rng: GameRng,
+  #[serde(skip)]
+  map_revision: u32,
+  #[serde(skip)]
+  dijkstra_maps: Vec<(Goal, bool, DijkstraMap)>,
+  // Fired during the last action, for the renderer to show
+  #[serde(skip)]
+  pub projectiles: Vec<Projectile>,
+  // Not saved, so a loaded game has to be given it again
+  #[serde(skip)]
+  pub content: Rc<Content>,
 }
 
 impl Game {
-  pub fn new() -> Game {
-    let map = Map::new();
-    let objects = Vec::new();
+  pub fn new(seed: u32, generator: Generator, content: Rc<Content>) -> Result<Game, String> {
+    let mut rng = seeded_rng(seed);
+    let map = Map::new(&generator, &content, &mut rng)?;
+    let objects = vec![Game::create_player()];
     let inventory = Vec::new();
     let messages = Messages::new();
+    let fov = Fov::new(MAP_WIDTH, MAP_HEIGHT);
 
-    let mut game = Game { map, objects, inventory, messages };
+    let mut game = Game {
+      map,
+      objects,
+      inventory,
+      messages,
+      fov,
+      seed,
+      generator,
+      turns: 0,
+      depth: 1,
+      rng,
+      map_revision: 0,
+      dijkstra_maps: vec![],
+      projectiles: vec![],
+      content,
+    };
     game.create_objects();
+    game.init_fov();
+    game.compute_fov();
 
-    game
+    game.messages.add(
+      Category::System,
+      "Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.",
+      RED,
+    );
+
+    Ok(game)
+  }
+
+  pub fn init_fov(&mut self) {
+    for y in 0..MAP_HEIGHT {
+      for x in 0..MAP_WIDTH {
+        self.fov.set(x, y, !self.map.tiles[x as usize][y as usize].block_sight());
+      }
+    }
+  }
+
+  pub fn compute_fov(&mut self) {
+    let (player_x, player_y) = self.objects[PLAYER].pos();
+    let radius = if self.objects[PLAYER].has_status(Effect::Blindness) { BLIND_RADIUS } else { TORCH_RADIUS };
+    self.fov.compute(player_x, player_y, radius, FOV_LIGHT_WALLS);
+
+    for y in 0..MAP_HEIGHT {
+      for x in 0..MAP_WIDTH {
+        if self.fov.is_in_fov(x, y) {
+          self.map.tiles[x as usize][y as usize].explored = true;
+        }
+      }
+    }
   }
 
   #[allow(clippy::ptr_arg)]
   fn is_blocked(&self, x: i32, y: i32) -> bool {
-    if self.map.tiles[x as usize][y as usize].blocked { return true; }
+    if self.map.tiles[x as usize][y as usize].blocked() { return true; }
 
     self.objects.iter().any(|obj| obj.blocks && obj.pos() == (x, y))
   }
 
-  fn create_objects(&mut self) {
-    let (x, y) = self.map.rooms[0].center();
+  // Monsters and items aren't placed in walls, on top of monsters or in lava
+  fn can_spawn_at(&self, x: i32, y: i32) -> bool {
+    !self.is_blocked(x, y) && self.map.tiles[x as usize][y as usize].kind.damage() == 0
+  }
 
-    let mut player = Object::new(x, y, '@', WHITE, "player", true);
+  // Like is_blocked, but a closed door can be opened so it doesn't count
+  fn is_impassable(&self, x: i32, y: i32) -> bool {
+    if !self.map.tiles[x as usize][y as usize].is_walkable() { return true; }
+
+    self.objects.iter().any(|obj| obj.blocks && obj.pos() == (x, y))
+  }
+
+  fn create_player() -> Object {
+    let mut player = Object::new(0, 0, '@', WHITE, "player", true);
 
     player.alive = true;
+    player.energy = ACTION_ENERGY;
     player.fighter = Some((
       Fighter {
-        max_hp: 30,
+        base_max_hp: 30,
         hp: 30,
-        defense: 2,
-        power: 5,
+        base_defense: 2,
+        base_power: 3,
+        damage: Dice { count: 1, sides: 4, bonus: 0 },
+        damage_type: DamageType::Physical,
+        accuracy: 2,
+        evasion: 0,
+        resistances: Resistances::default(),
+        xp: 0,
       },
       Death::Player
     ));
 
-    self.objects.push(player);
+    player
+  }
+
+  fn create_objects(&mut self) {
+    let areas = self.map.spawn_areas();
+
+    let (x, y) = areas[0].center;
+    self.objects[PLAYER].set_pos(x, y);
+
+    let max_monsters = from_depth(MAX_ROOM_MONSTERS, self.depth);
+    let max_items = from_depth(MAX_ROOM_ITEMS, self.depth);
 
-    for room in self.map.rooms.iter() {
-      let num_monsters = rand::thread_rng().gen_range(0, MAX_ROOM_MONSTERS + 1);
+    for area in &areas {
+      let num_monsters = self.rng.gen_range(0, max_monsters + 1);
 
       for _ in 0..num_monsters {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
-
-        if self.is_blocked(x, y) { continue; }
-
-        let monster_type = if rand::random::<f32>() < 0.8 { "orc" } else { "troll" };
-
-        let mut monster;
-
-        if monster_type == "orc" {
-          monster = Object::new(x, y, 'o', colors::DESATURATED_GREEN, "orc", true);
-          monster.fighter = Some((
-            Fighter {
-              max_hp: 10,
-              hp: 10,
-              defense: 0,
-              power: 3,
-            },
-            Death::Monster,
-          ));
-          monster.ai = Some(Ai::Basic);
-        } else {
-          monster = Object::new(x, y, 'T', colors::DARKER_GREEN, "troll", true);
-          monster.fighter = Some((
-            Fighter {
-              max_hp: 16,
-              hp: 16,
-              defense: 1,
-              power: 4,
-            },
-            Death::Monster,
-          ));
-          monster.ai = Some(Ai::Basic);
-        };
+        let (x, y) = area.tiles[self.rng.gen_range(0, area.tiles.len())];
 
-        monster.alive = true;
+        if !self.can_spawn_at(x, y) { continue; }
 
-        self.objects.push(monster);
+        if let Some(monster) = self.create_monster(x, y) {
+          self.objects.push(monster);
+        }
       }
 
-      let num_items = rand::thread_rng().gen_range(0, MAX_ROOM_ITEMS + 1);
+      let num_items = self.rng.gen_range(0, max_items + 1);
 
       for _ in 0..num_items {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+        let (x, y) = area.tiles[self.rng.gen_range(0, area.tiles.len())];
 
-        if self.is_blocked(x, y) { continue; }
+        if !self.can_spawn_at(x, y) { continue; }
 
-        let mut object = Object::new(x, y, '!', VIOLET, "healing potion", false);
-        object.item = Some(Item::Heal);
+        if let Some(item) = self.create_item(x, y, FLOOR_TABLE) {
+          self.objects.push(item);
+        }
+      }
+    }
+
+    for ((x, y), marker) in self.map.spawns.clone() {
+      if marker == Marker::Monster && !self.can_spawn_at(x, y) { continue; }
+
+      let object = match marker {
+        Marker::Monster => self.create_monster(x, y),
+        Marker::Item => self.create_item(x, y, TREASURE_TABLE),
+        Marker::Key => {
+          let mut key = Object::new(x, y, '-', GOLD, "key", false);
+          key.item = Some(Item::Key);
+          Some(key)
+        }
+      };
 
+      if let Some(object) = object {
         self.objects.push(object);
       }
     }
+
+    let (stairs_x, stairs_y) = self.map.stairs;
+    let mut stairs = Object::new(stairs_x, stairs_y, '>', WHITE, "stairs", false);
+    stairs.always_visible = true;
+
+    self.objects.push(stairs);
+  }
+
+  fn create_monster(&mut self, x: i32, y: i32) -> Option<Object> {
+    let library = &self.content.monsters;
+    let monster_chances = &mut library
+      .iter()
+      .enumerate()
+      .map(|(index, template)| Weighted {
+        weight: from_depth(&template.spawn_weight, self.depth) as u32,
+        item: index,
+      })
+      .collect::<Vec<_>>();
+
+    if monster_chances.iter().all(|chance| chance.weight == 0) { return None; }
+
+    let choice = WeightedChoice::new(monster_chances);
+
+    Some(library[choice.ind_sample(&mut self.rng)].spawn(x, y))
+  }
+
+  fn create_item(&mut self, x: i32, y: i32, table: &str) -> Option<Object> {
+    let loot = &self.content.loot;
+    let table = loot.table(table)?;
+
+    let item_chances = &mut table
+      .entries
+      .iter()
+      .map(|entry| Weighted { weight: from_depth(&entry.weight, self.depth) as u32, item: entry.item.as_str() })
+      .collect::<Vec<_>>();
+
+    if item_chances.iter().all(|chance| chance.weight == 0) { return None; }
+
+    let choice = WeightedChoice::new(item_chances);
+
+    loot.item(choice.ind_sample(&mut self.rng)).map(|template| template.spawn(x, y))
+  }
+
+  fn next_level(&mut self) -> Result<(), String> {
+    let map = Map::new(&self.generator, &self.content, &mut self.rng)?;
+
+    self.depth += 1;
+    self.messages.add(Category::System, "You descend deeper into the heart of the dungeon...", RED);
+
+    self.map = map;
+    self.map_revision += 1;
+    self.objects.truncate(1);
+    self.create_objects();
+    self.init_fov();
+
+    Ok(())
   }
 
   #[allow(clippy::ptr_arg)]
   pub fn move_by(&mut self, id: usize, dx: i32, dy: i32) {
     let (x, y) = self.objects[id].pos();
 
-    if !self.is_blocked(x + dx, y + dy) {
+    if self.map.tiles[(x + dx) as usize][(y + dy) as usize].door().is_some_and(|door| door != Door::Open) {
+      self.open_door(id, x + dx, y + dy);
+    } else if !self.is_blocked(x + dx, y + dy) {
       self.objects[id].set_pos(x + dx, y + dy);
+      self.enter_tile(id);
+    }
+  }
+
+  fn enter_tile(&mut self, id: usize) {
+    let (x, y) = self.objects[id].pos();
+    let damage = self.map.tiles[x as usize][y as usize].kind.damage();
+
+    if damage == 0 { return; }
+
+    let damage = self.resist(id, damage, DamageType::Fire);
+    if damage == 0 { return; }
+
+    if id == PLAYER {
+      self.messages.add(Category::Combat, format!("The lava burns you for {} hit points!", damage), ORANGE);
+    } else if self.fov.is_in_fov(x, y) {
+      self.messages.add(Category::Combat, format!("The {} is burned by the lava!", self.objects[id].name), ORANGE);
+    }
+
+    self.inflict_damage(id, damage, id);
+  }
+
+  fn open_door(&mut self, id: usize, x: i32, y: i32) {
+    match self.map.tiles[x as usize][y as usize].door() {
+      Some(Door::Closed) => {}
+      Some(Door::Locked) if id == PLAYER => {
+        let key = self.inventory.iter().position(|obj| matches!(obj.item, Some(Item::Key)));
+
+        match key {
+          Some(key) => {
+            self.inventory.remove(key);
+            self.messages.add(Category::System, "You unlock the door with your key.", LIGHT_YELLOW);
+          }
+          None => {
+            self.messages.add(Category::System, "The door is locked.", WHITE);
+            return;
+          }
+        }
+      }
+      _ => return,
     }
+
+    if id == PLAYER {
+      self.messages.add(Category::System, "You open the door.", WHITE);
+    } else if self.fov.is_in_fov(self.objects[id].x, self.objects[id].y) {
+      self.messages.add(Category::System, format!("The {} opens a door.", self.objects[id].name), WHITE);
+    }
+
+    self.set_door(x, y, Door::Open);
+  }
+
+  // Updates the tile and the FOV map without rebuilding either
+  fn set_door(&mut self, x: i32, y: i32, door: Door) {
+    self.map.tiles[x as usize][y as usize].set_door(door);
+    self.fov.set(x, y, door == Door::Open);
+    self.map_revision += 1;
+  }
+
+  fn close_doors(&mut self) -> bool {
+    let (player_x, player_y) = self.objects[PLAYER].pos();
+    let mut closed = false;
+
+    for x in (player_x - 1)..=(player_x + 1) {
+      for y in (player_y - 1)..=(player_y + 1) {
+        if self.map.tiles[x as usize][y as usize].door() != Some(Door::Open) { continue; }
+        if self.objects.iter().any(|obj| obj.pos() == (x, y)) { continue; }
+
+        self.set_door(x, y, Door::Closed);
+        closed = true;
+      }
+    }
+
+    if closed {
+      self.messages.add(Category::System, "You close the door.", WHITE);
+    } else {
+      self.messages.add(Category::System, "There is no open door next to you.", WHITE);
+    }
+
+    closed
   }
 
   pub fn player_move_or_attack(&mut self, dx: i32, dy: i32) {
     let x = self.objects[PLAYER].x + dx;
     let y = self.objects[PLAYER].y + dy;
 
-    let target_id = self.objects.iter().position(|obj| obj.pos() == (x, y) && obj.fighter.is_some());
+    let target_id = self.objects
+      .iter()
+      .enumerate()
+      .position(|(id, obj)| id != PLAYER && obj.pos() == (x, y) && obj.fighter.is_some());
     match target_id {
       Some(target_id) => {
         self.attack(PLAYER, target_id);
@@ -179,6 +432,20 @@ impl Game {
     let this = &self.objects[id];
     let target = &self.objects[other_id];
 
+    let occupied: Vec<(i32, i32)> = self.objects
+      .iter()
+      .enumerate()
+      .filter(|&(obj_id, obj)| obj.blocks && obj_id != id && obj_id != other_id)
+      .map(|(_, obj)| obj.pos())
+      .collect();
+
+    let path = find_path(&self.map, &occupied, this.pos(), target.pos(), MAX_PATH_LENGTH);
+
+    if let Some(&(x, y)) = path.as_ref().and_then(|path| path.first()) {
+      self.move_by(id, x - this.x, y - this.y);
+      return;
+    }
+
     let dx = target.x - this.x;
     let dy = target.y - this.y;
     let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
@@ -189,96 +456,905 @@ impl Game {
     self.move_by(id, nx, ny);
   }
 
-  pub fn pick_item_up(&mut self, object_id: usize) {
+  pub fn pick_item_up(&mut self, object_id: usize) -> bool {
     if self.inventory.len() >= 26 {
       self.messages.add(
+        Category::Items,
         format!(
           "Cannot pick up {}. Inventory is full.",
           self.objects[object_id].name,
         ),
         RED
       );
+
+      false
     } else {
       let item = self.objects.swap_remove(object_id);
-      self.messages.add(format!("You picked up a {}!", item.name), GREEN);
+      self.messages.add(Category::Items, format!("You picked up a {}!", item.name), GREEN);
       self.inventory.push(item);
+
+      true
     }
   }
 
-  fn cast_heal(&mut self, _inventory_id: usize) -> UseResult {
+  fn equipped(&self, id: usize) -> Vec<Equipment> {
+    if id != PLAYER { return vec![]; }
+
+    self.inventory
+      .iter()
+      .filter_map(|item| item.equipment)
+      .filter(|equipment| equipment.equipped)
+      .collect()
+  }
+
+  pub fn power(&self, id: usize) -> i32 {
+    let base_power = self.objects[id].fighter.map_or(0, |(f, _)| f.base_power);
+    let strength = self.objects[id].statuses
+      .iter()
+      .map(|status| match status.effect {
+        Effect::Strength { bonus } => bonus,
+        _ => 0,
+      })
+      .sum::<i32>();
+
+    base_power + strength + self.equipped(id).iter().map(|e| e.power_bonus).sum::<i32>()
+  }
+
+  pub fn defense(&self, id: usize) -> i32 {
+    let base_defense = self.objects[id].fighter.map_or(0, |(f, _)| f.base_defense);
+    base_defense + self.equipped(id).iter().map(|e| e.defense_bonus).sum::<i32>()
+  }
+
+  pub fn max_hp(&self, id: usize) -> i32 {
+    let base_max_hp = self.objects[id].fighter.map_or(0, |(f, _)| f.base_max_hp);
+    base_max_hp + self.equipped(id).iter().map(|e| e.max_hp_bonus).sum::<i32>()
+  }
+
+  fn closest_monster(&self, max_range: f32) -> Option<usize> {
+    let player = &self.objects[PLAYER];
+
+    self.objects
+      .iter()
+      .enumerate()
+      .filter(|&(id, obj)| {
+        id != PLAYER
+          && obj.fighter.is_some()
+          && obj.ai.is_some()
+          && self.fov.is_in_fov(obj.x, obj.y)
+          && player.distance_to(obj) <= max_range
+      })
+      .min_by(|(_, a), (_, b)| player.distance_to(a).partial_cmp(&player.distance_to(b)).unwrap())
+      .map(|(id, _)| id)
+  }
+
+  fn cast_heal(&mut self, amount: Dice) -> UseResult {
     if let Some(fighter) = self.objects[PLAYER].fighter {
-      if fighter.0.hp == fighter.0.max_hp {
-        self.messages.add("You are already at full health.", RED);
+      let max_hp = self.max_hp(PLAYER);
+
+      if fighter.0.hp == max_hp {
+        self.messages.add(Category::Items, "You are already at full health.", RED);
         return UseResult::Cancelled;
       }
 
-      self.messages.add("Your wounds start to feel better!", LIGHT_VIOLET);
-      self.objects[PLAYER].heal(HEAL_AMOUNT);
+      self.messages.add(Category::Items, "Your wounds start to feel better!", LIGHT_VIOLET);
+      let amount = amount.roll(&mut self.rng).max(0);
+      self.objects[PLAYER].heal(amount, max_hp);
       return UseResult::UsedUp;
     }
 
     UseResult::Cancelled
   }
 
-  pub fn use_item(&mut self, inventory_id: usize) {
-    if let Some(item) = &self.inventory[inventory_id].item {
-      let on_use = match item {
-        Item::Heal => Game::cast_heal,
+  fn cast_lightning(&mut self, damage: Dice, range: i32) -> UseResult {
+    let monster_id = match self.closest_monster(range as f32) {
+      Some(monster_id) => monster_id,
+      None => {
+        self.messages.add(Category::Items, "No enemy is close enough to strike.", RED);
+        return UseResult::Cancelled;
+      }
+    };
+
+    let damage = damage.roll(&mut self.rng).max(0);
+    let damage = self.resist(monster_id, damage, DamageType::Lightning);
+    if damage == 0 { return UseResult::UsedUp; }
+
+    self.messages.add(
+      Category::Combat,
+      format!(
+        "A lightning bolt strikes the {} with a loud thunder! The damage is {} hit points.",
+        self.objects[monster_id].name,
+        damage,
+      ),
+      LIGHT_BLUE,
+    );
+    self.inflict_damage(monster_id, damage, PLAYER);
+
+    UseResult::UsedUp
+  }
+
+  fn cast_fireball(&mut self, target: Option<(i32, i32)>, damage: Dice, radius: i32) -> UseResult {
+    let (x, y) = match target {
+      Some(target) if self.fov.is_in_fov(target.0, target.1) => target,
+      _ => return UseResult::Cancelled,
+    };
+
+    self.messages.add(
+      Category::Combat,
+      format!("The fireball explodes, burning everything within {} tiles!", radius),
+      ORANGE,
+    );
+
+    for id in 0..self.objects.len() {
+      if self.objects[id].fighter.is_some() && self.objects[id].distance(x, y) <= radius as f32 {
+        let damage = damage.roll(&mut self.rng).max(0);
+        let damage = self.resist(id, damage, DamageType::Fire);
+        if damage == 0 { continue; }
+
+        let obj = &self.objects[id];
+        self.messages.add(
+          Category::Combat,
+          format!("The {} gets burned for {} hit points.", obj.name, damage),
+          ORANGE,
+        );
+        self.inflict_damage(id, damage, PLAYER);
+      }
+    }
+
+    UseResult::UsedUp
+  }
+
+  fn cast_confuse(&mut self, target: Option<(i32, i32)>, range: i32, duration: i32) -> UseResult {
+    let monster_id = target.and_then(|(x, y)| {
+      self.objects.iter().position(|obj| {
+        obj.pos() == (x, y)
+          && obj.ai.is_some()
+          && self.fov.is_in_fov(x, y)
+          && self.objects[PLAYER].distance(x, y) <= range as f32
+      })
+    });
+
+    let monster_id = match monster_id {
+      Some(monster_id) => monster_id,
+      None => {
+        self.messages.add(Category::Items, "There is no enemy there to confuse.", RED);
+        return UseResult::Cancelled;
+      }
+    };
+
+    self.objects[monster_id].add_status(Effect::Confusion, duration);
+
+    self.messages.add(
+      Category::Combat,
+      format!("The eyes of the {} look vacant, as it starts to stumble around!", self.objects[monster_id].name),
+      LIGHT_GREEN,
+    );
+
+    UseResult::UsedUp
+  }
+
+  fn cast_status(&mut self, effect: Effect, duration: i32) -> UseResult {
+    self.objects[PLAYER].add_status(effect, duration);
+    self.messages.add(Category::Items, format!("You are {}!", effect.describe()), effect.color());
+
+    if effect == Effect::Blindness {
+      self.compute_fov();
+    }
+
+    UseResult::UsedUp
+  }
+
+  fn toggle_equipment(&mut self, inventory_id: usize) -> UseResult {
+    let equipment = match self.inventory[inventory_id].equipment {
+      Some(equipment) => equipment,
+      None => return UseResult::Cancelled,
+    };
+
+    if equipment.equipped {
+      self.dequip(inventory_id);
+    } else {
+      let current = self.inventory
+        .iter()
+        .position(|item| item.equipment.is_some_and(|e| e.equipped && e.slot == equipment.slot));
+
+      if let Some(current) = current {
+        self.dequip(current);
+      }
+
+      self.equip(inventory_id);
+    }
+
+    UseResult::Kept
+  }
+
+  fn equip(&mut self, inventory_id: usize) {
+    let item = &mut self.inventory[inventory_id];
+
+    if let Some(equipment) = item.equipment.as_mut() {
+      equipment.equipped = true;
+      self.messages.add(Category::Items, format!("Equipped {} on {}.", item.name, equipment.slot), LIGHT_GREEN);
+    }
+  }
+
+  fn dequip(&mut self, inventory_id: usize) {
+    let item = &mut self.inventory[inventory_id];
+
+    if let Some(equipment) = item.equipment.as_mut() {
+      equipment.equipped = false;
+      self.messages.add(Category::Items, format!("Dequipped {} from {}.", item.name, equipment.slot), LIGHT_YELLOW);
+    }
+
+    let max_hp = self.max_hp(PLAYER);
+    if let Some((fighter, _)) = self.objects[PLAYER].fighter.as_mut() {
+      fighter.hp = std::cmp::min(fighter.hp, max_hp);
+    }
+  }
+
+  fn use_key(&mut self) -> UseResult {
+    self.messages.add(Category::Items, "Walk into a locked door to unlock it with the key.", WHITE);
+    UseResult::Kept
+  }
+
+  // Returns whether using the item took any time
+  pub fn use_item(&mut self, inventory_id: usize, target: Option<(i32, i32)>) -> bool {
+    if let Some(item) = self.inventory[inventory_id].item {
+      let result = match item {
+        Item::Heal { amount } => self.cast_heal(amount),
+        Item::Lightning { damage, range } => self.cast_lightning(damage, range),
+        Item::Fireball { damage, radius } => self.cast_fireball(target, damage, radius),
+        Item::Confuse { range, duration } => self.cast_confuse(target, range, duration),
+        Item::Status { effect, duration } => self.cast_status(effect, duration),
+        Item::Equip => self.toggle_equipment(inventory_id),
+        Item::Key => self.use_key(),
       };
 
-      match on_use(self, inventory_id) {
+      match result {
         UseResult::UsedUp => {
           self.inventory.remove(inventory_id);
+          true
         }
+        UseResult::Kept => item == Item::Equip,
         UseResult::Cancelled => {
-          self.messages.add("Cancelled", WHITE);
+          self.messages.add(Category::Items, "Cancelled", WHITE);
+          false
         }
       }
     } else {
       self.messages.add(
+        Category::Items,
         format!("The {} cannot be used.", self.inventory[inventory_id].name),
         WHITE,
       );
+
+      false
     }
   }
 
-  pub fn attack(&mut self, id: usize, other_id: usize) {
-    let (source, target) = mut_two(id, other_id, &mut self.objects);
-
-    let damage = source.fighter.unwrap().0.power - target.fighter.unwrap().0.defense;
-    if damage > 0 {
-      self.messages.add(format!("{} attacks {} for {} hit points", source.name, target.name, damage), WHITE);
-      if !target.take_damage(damage) {
-        match target.fighter.unwrap().1 {
-          Death::Player => player_death(target, &mut self.messages),
-          Death::Monster => monster_death(target, &mut self.messages),
+  fn resistance(&self, id: usize, kind: DamageType) -> i32 {
+    let own = self.objects[id].fighter.map_or(0, |(f, _)| f.resistances.get(kind));
+    let worn = self.equipped(id).iter().map(|e| e.resistances.get(kind)).sum::<i32>();
+
+    (own + worn).min(100)
+  }
+
+  // Scales damage by the target's resistance to its type, saying so whenever
+  // the resistance makes a difference
+  fn resist(&mut self, id: usize, damage: i32, kind: DamageType) -> i32 {
+    let resistance = self.resistance(id, kind);
+    if resistance == 0 || damage <= 0 { return damage; }
+
+    let resisted = damage * (100 - resistance) / 100;
+    let (x, y) = self.objects[id].pos();
+
+    if id != PLAYER && !self.fov.is_in_fov(x, y) { return resisted; }
+
+    let name = &self.objects[id].name;
+    let source = kind.source();
+    let message = match (id == PLAYER, resistance) {
+      (true, 100) => format!("You shrug off {}!", source),
+      (false, 100) => format!("The {} shrugs off {}!", name, source),
+      (true, r) if r > 0 => format!("You resist {}.", source),
+      (false, r) if r > 0 => format!("The {} resists {}.", name, source),
+      (true, _) => format!("You are hurt badly by {}!", source),
+      (false, _) => format!("The {} is hurt badly by {}!", name, source),
+    };
+    self.messages.add(Category::Combat, message, LIGHT_BLUE);
+
+    resisted
+  }
+
+  fn inflict_damage(&mut self, id: usize, damage: i32, attacker_id: usize) {
+    let (fighter, death) = match self.objects[id].fighter {
+      Some(fighter) => fighter,
+      None => return,
+    };
+
+    if self.objects[id].take_damage(damage) { return; }
+
+    match death {
+      Death::Player => player_death(&mut self.objects[id], &mut self.messages),
+      Death::Monster => {
+        monster_death(&mut self.objects[id], &mut self.messages);
+
+        if let Some((attacker, _)) = self.objects[attacker_id].fighter.as_mut() {
+          attacker.xp += fighter.xp;
+        }
+
+        if attacker_id == PLAYER {
+          self.messages.add(Category::Combat, format!("You gain {} experience points.", fighter.xp), ORANGE);
         }
       }
+    }
+  }
+
+  // An equipped weapon's damage replaces the fighter's own
+  fn damage_dice(&self, id: usize) -> Dice {
+    let own = self.objects[id].fighter.map_or(Dice::flat(0), |(f, _)| f.damage);
+
+    self.equipped(id).iter().find_map(|e| e.damage).unwrap_or(own)
+  }
+
+  pub fn attack(&mut self, id: usize, other_id: usize) {
+    self.strike(id, other_id, "attacks");
+  }
+
+  // Resolves a melee or ranged attack. `verb` describes it in the messages.
+  fn strike(&mut self, id: usize, other_id: usize, verb: &str) {
+    let attack = Attack {
+      accuracy: self.objects[id].fighter.map_or(0, |(f, _)| f.accuracy),
+      damage: self.damage_dice(id),
+      power: self.power(id),
+    };
+    let defense = Defense {
+      evasion: self.objects[other_id].fighter.map_or(0, |(f, _)| f.evasion),
+      armor: self.defense(other_id),
+    };
+
+    let outcome = combat::resolve(&mut self.rng, &attack, &defense);
+
+    let damage_type = self.objects[id].fighter.map_or(DamageType::Physical, |(f, _)| f.damage_type);
+    let outcome = match outcome {
+      Outcome::Hit(damage) => Outcome::Hit(self.resist(other_id, damage, damage_type)),
+      Outcome::Critical(damage) => Outcome::Critical(self.resist(other_id, damage, damage_type)),
+      outcome => outcome,
+    };
+    let (source, target) = (&self.objects[id].name, &self.objects[other_id].name);
+
+    match outcome {
+      Outcome::Hit(damage) if damage > 0 => {
+        self.messages.add(Category::Combat, format!("{} {} {} for {} hit points", source, verb, target, damage), WHITE);
+        self.inflict_damage(other_id, damage, id);
+      }
+      Outcome::Critical(damage) if damage > 0 => {
+        self.messages.add(
+          Category::Combat,
+          format!("{} lands a critical hit on {} for {} hit points!", source, target, damage),
+          LIGHT_YELLOW,
+        );
+        self.inflict_damage(other_id, damage, id);
+      }
+      Outcome::Hit(_) | Outcome::Critical(_) => {
+        self.messages.add(Category::Combat, format!("{} {} {} but it has no effect", source, verb, target), WHITE);
+      }
+      Outcome::Miss => {
+        self.messages.add(Category::Combat, format!("{} {} {} but misses", source, verb, target), LIGHT_GREY);
+      }
+      Outcome::Fumble => {
+        self.messages.add(Category::Combat, format!("{} fumbles the attack on {}!", source, target), LIGHT_GREY);
+      }
+    }
+  }
+
+  pub fn goal_positions(&self, goal: Goal) -> Vec<(i32, i32)> {
+    match goal {
+      Goal::Player => vec![self.objects[PLAYER].pos()],
+      Goal::Items => self.objects.iter().filter(|obj| obj.item.is_some()).map(|obj| obj.pos()).collect(),
+      Goal::Stairs => vec![self.map.stairs],
+    }
+  }
+
+  // Maps are cached, and only rebuilt once their goals have moved or the map
+  // has changed underneath them
+  fn dijkstra_index(&mut self, goal: Goal, flee: bool) -> usize {
+    let goals = self.goal_positions(goal);
+
+    let cached = self.dijkstra_maps.iter().position(|&(g, f, _)| g == goal && f == flee);
+
+    if let Some(index) = cached {
+      let map = &self.dijkstra_maps[index].2;
+      if map.goals == goals && map.revision == self.map_revision { return index; }
+    }
+
+    let dijkstra_map = if flee {
+      let chase = self.dijkstra_index(goal, false);
+      self.dijkstra_maps[chase].2.inverted(&self.map)
     } else {
-      self.messages.add(format!("{} attacks {} but it has no effect", source.name, target.name), WHITE);
+      DijkstraMap::new(&self.map, &goals, self.map_revision)
+    };
+
+    match cached {
+      Some(index) => {
+        self.dijkstra_maps[index].2 = dijkstra_map;
+        index
+      }
+      None => {
+        self.dijkstra_maps.push((goal, flee, dijkstra_map));
+        self.dijkstra_maps.len() - 1
+      }
     }
   }
 
-  fn ai_turn(&mut self, id: usize, fov_map: &FovMap) {
+  pub fn refresh_dijkstra_map(&mut self, goal: Goal, flee: bool) {
+    self.dijkstra_index(goal, flee);
+  }
+
+  // A cached map, as last refreshed. None until something has asked for it.
+  pub fn dijkstra_map(&self, goal: Goal, flee: bool) -> Option<&DijkstraMap> {
+    self.dijkstra_maps
+      .iter()
+      .find(|&&(g, f, _)| g == goal && f == flee)
+      .map(|(_, _, map)| map)
+  }
+
+  fn move_downhill(&mut self, id: usize, goal: Goal, flee: bool) -> bool {
+    let index = self.dijkstra_index(goal, flee);
+    let (x, y) = self.objects[id].pos();
+
+    let step = self.dijkstra_maps[index].2.downhill(x, y, |nx, ny| self.is_impassable(nx, ny));
+
+    match step {
+      Some((nx, ny)) => {
+        self.move_by(id, nx - x, ny - y);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn player_distance(&mut self, id: usize) -> f32 {
+    let index = self.dijkstra_index(Goal::Player, false);
+    let (x, y) = self.objects[id].pos();
+
+    self.dijkstra_maps[index].2.value(x, y)
+  }
+
+  fn is_fleeing(&self, id: usize) -> bool {
+    self.objects[id].fighter.is_some_and(|(f, _)| f.hp * 4 < self.max_hp(id))
+  }
+
+  // Monsters look from where they stand rather than through the player's
+  // FOV, which a blind player shrinks to almost nothing
+  fn sees_player(&self, id: usize) -> bool {
+    let monster = &self.objects[id];
+    let player = self.objects[PLAYER].pos();
+
+    !monster.has_status(Effect::Blindness)
+      && monster.distance_to(&self.objects[PLAYER]) <= TORCH_RADIUS as f32
+      && Line::new(monster.pos(), player)
+        .take_while(|&pos| pos != player)
+        .all(|(x, y)| !self.map.tiles[x as usize][y as usize].block_sight())
+  }
+
+  // Whether a projectile could fly from one tile to another without being
+  // stopped by a wall or hitting someone standing in between
+  fn has_line_of_fire(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+    Line::new(from, to)
+      .take_while(|&pos| pos != to)
+      .all(|(x, y)| {
+        !self.map.tiles[x as usize][y as usize].kind.blocks_projectiles()
+          && !self.objects.iter().any(|obj| obj.pos() == (x, y) && obj.blocks && obj.fighter.is_some())
+      })
+  }
+
+  // Sends a projectile from the object to a tile. It hits the first fighter
+  // in its way, which isn't always the one it was aimed at.
+  fn fire(&mut self, id: usize, target: (i32, i32)) {
+    let mut path = vec![];
+    let mut hit = None;
+
+    for (x, y) in Line::new(self.objects[id].pos(), target) {
+      if self.map.tiles[x as usize][y as usize].kind.blocks_projectiles() { break; }
+
+      path.push((x, y));
+
+      hit = self.objects.iter().position(|obj| obj.pos() == (x, y) && obj.blocks && obj.fighter.is_some());
+      if hit.is_some() { break; }
+    }
+
+    let damage_type = self.objects[id].fighter.map_or(DamageType::Physical, |(f, _)| f.damage_type);
+    self.projectiles.push(Projectile { path, color: damage_type.color() });
+
+    match hit {
+      Some(other_id) => self.strike(id, other_id, "shoots"),
+      None => {
+        self.messages.add(Category::Combat, format!("{} shoots and misses", self.objects[id].name), LIGHT_GREY);
+      }
+    }
+  }
+
+  fn ai_ranged(&mut self, id: usize, range: i32, keep_distance: i32) {
     let (ai_x, ai_y) = self.objects[id].pos();
 
-    if fov_map.is_in_fov(ai_x, ai_y) {
-      if self.objects[id].distance_to(&self.objects[PLAYER]) >= 2.0 {
+    if !self.sees_player(id) || !self.objects[PLAYER].alive {
+      self.ai_basic(id);
+      return;
+    }
+
+    let player = self.objects[PLAYER].pos();
+    let distance = self.objects[id].distance_to(&self.objects[PLAYER]);
+
+    if distance < keep_distance as f32 && self.move_downhill(id, Goal::Player, true) {
+      return;
+    }
+
+    // With someone in the way, it closes in instead of shooting them
+    if distance <= range as f32 && self.has_line_of_fire((ai_x, ai_y), player) {
+      self.fire(id, player);
+    } else if distance < 2.0 {
+      self.attack(id, PLAYER);
+    } else {
+      self.move_towards(id, PLAYER);
+    }
+  }
+
+  fn ai_basic(&mut self, id: usize) {
+    let adjacent = self.objects[id].distance_to(&self.objects[PLAYER]) < 2.0;
+    let blind = self.objects[id].has_status(Effect::Blindness);
+
+    if self.sees_player(id) {
+      let fled = self.is_fleeing(id) && self.move_downhill(id, Goal::Player, true);
+
+      if fled {
+        return;
+      }
+
+      if !adjacent {
         self.move_towards(id, PLAYER);
       } else if self.objects[id].fighter.is_some()
-        && self.objects[PLAYER].fighter.map_or(false, |f| f.0.hp > 0) {
+        && self.objects[PLAYER].fighter.is_some_and(|f| f.0.hp > 0) {
         self.attack(id, PLAYER);
       }
+    } else if blind && adjacent {
+      self.attack(id, PLAYER);
+    } else if self.player_distance(id) <= HEARING_RANGE {
+      self.move_downhill(id, Goal::Player, false);
+    }
+  }
+
+  // Any of the eight directions, but never standing still
+  fn random_direction(&mut self) -> (i32, i32) {
+    loop {
+      let dx = self.rng.gen_range(-1, 2);
+      let dy = self.rng.gen_range(-1, 2);
+
+      if (dx, dy) != (0, 0) { return (dx, dy); }
+    }
+  }
+
+  fn stumble(&mut self, id: usize) {
+    let (dx, dy) = self.random_direction();
+    self.move_by(id, dx, dy);
+  }
+
+  fn ai_turn(&mut self, id: usize) {
+    if self.objects[id].has_status(Effect::Confusion) {
+      self.stumble(id);
+      return;
+    }
+
+    match self.objects[id].ai.clone() {
+      Some(Ai::Basic) => self.ai_basic(id),
+      Some(Ai::Ranged { range, keep_distance }) => self.ai_ranged(id, range, keep_distance),
+      None => {}
+    }
+  }
+
+  pub fn level_up_xp(&self) -> i32 {
+    LEVEL_UP_BASE + self.objects[PLAYER].level * LEVEL_UP_FACTOR
+  }
+
+  pub fn can_level_up(&self) -> bool {
+    self.objects[PLAYER].alive
+      && self.objects[PLAYER].fighter.is_some_and(|f| f.0.xp >= self.level_up_xp())
+  }
+
+  fn level_up(&mut self, stat: Stat) {
+    let required_xp = self.level_up_xp();
+    let player = &mut self.objects[PLAYER];
+
+    if let Some((fighter, _)) = player.fighter.as_mut() {
+      player.level += 1;
+      fighter.xp -= required_xp;
+
+      match stat {
+        Stat::Hp => {
+          fighter.base_max_hp += 20;
+          fighter.hp += 20;
+        }
+        Stat::Power => fighter.base_power += 1,
+        Stat::Defense => fighter.base_defense += 1,
+      }
+
+      self.messages.add(
+        Category::System,
+        format!("Your battle skills grow stronger! You reached level {}!", player.level),
+        YELLOW,
+      );
+    }
+  }
+
+  fn tick_statuses(&mut self, id: usize) {
+    let statuses = std::mem::take(&mut self.objects[id].statuses);
+    let mut remaining = Vec::with_capacity(statuses.len());
+
+    for mut status in statuses {
+      match status.effect {
+        Effect::Poison { damage } => {
+          let damage = self.resist(id, damage, DamageType::Poison);
+
+          if id == PLAYER && damage > 0 {
+            self.messages.add(Category::Combat, format!("The poison hurts you for {} hit points.", damage), GREEN);
+          }
+          self.inflict_damage(id, damage, id);
+        }
+        Effect::Regeneration { amount } => {
+          let max_hp = self.max_hp(id);
+          self.objects[id].heal(amount, max_hp);
+        }
+        _ => {}
+      }
+
+      status.turns -= 1;
+
+      // Poison can kill partway through, and the dead have nothing left to wear off
+      if !self.objects[id].alive { break; }
+
+      if status.turns > 0 {
+        remaining.push(status);
+      } else if id == PLAYER {
+        self.messages.add(Category::System, format!("You are no longer {}.", status.effect.describe()), LIGHT_YELLOW);
+      } else if self.fov.is_in_fov(self.objects[id].x, self.objects[id].y) {
+        let name = &self.objects[id].name;
+        self.messages.add(Category::Combat, format!("The {} is no longer {}!", name, status.effect.describe()), RED);
+      }
+    }
+
+    // Dying clears every status, so there is nothing left to keep
+    if self.objects[id].alive {
+      self.objects[id].statuses = remaining;
+    }
+  }
+
+  pub fn speed(&self, id: usize) -> i32 {
+    let object = &self.objects[id];
+    let mut speed = object.speed;
+
+    if object.has_status(Effect::Haste) { speed *= 2; }
+    if object.has_status(Effect::Slow) { speed /= 2; }
+
+    speed.max(1)
+  }
+
+  // A step costs more energy the harder the ground it ends on. Anything else
+  // the actor did in its place, like attacking or waiting, is a normal action.
+  fn action_energy(&self, id: usize, start: (i32, i32)) -> i32 {
+    let (x, y) = self.objects[id].pos();
+
+    if (x, y) == start {
+      ACTION_ENERGY
+    } else {
+      ACTION_ENERGY * self.map.tiles[x as usize][y as usize].kind.move_cost() as i32
     }
   }
 
-  pub fn update_objects(&mut self, fov_map: &FovMap) {
+  // Runs one turn of the world: statuses tick, every actor gains energy, and
+  // monsters act for as long as they have enough of it
+  fn update_objects(&mut self) {
     for id in 0..self.objects.len() {
+      if !self.objects[id].alive { continue; }
+
+      if !self.objects[id].statuses.is_empty() {
+        self.tick_statuses(id);
+      }
+
+      if id != PLAYER && self.objects[id].ai.is_none() { continue; }
+
+      self.objects[id].energy += self.speed(id);
+
       if id == PLAYER { continue; }
 
-      if self.objects[id].alive && self.objects[id].ai.is_some() {
-        self.ai_turn(id, fov_map);
+      while self.objects[id].alive && self.objects[id].ai.is_some() && self.objects[id].energy >= ACTION_ENERGY {
+        let start = self.objects[id].pos();
+        self.ai_turn(id);
+        self.objects[id].energy -= self.action_energy(id, start);
       }
     }
   }
+
+  // Fails only if the next level can't be generated
+  pub fn perform(&mut self, action: Action) -> Result<bool, String> {
+    if !self.objects[PLAYER].alive { return Ok(false); }
+
+    self.projectiles.clear();
+
+    let start = self.objects[PLAYER].pos();
+
+    let energy = match action {
+      Action::Move(dx, dy) => {
+        if self.objects[PLAYER].has_status(Effect::Confusion) {
+          let (dx, dy) = self.random_direction();
+          self.player_move_or_attack(dx, dy);
+        } else {
+          self.player_move_or_attack(dx, dy);
+        }
+
+        self.action_energy(PLAYER, start)
+      }
+      Action::PickUp => {
+        let item_id = self.objects
+          .iter()
+          .position(|obj| obj.pos() == self.objects[PLAYER].pos() && obj.item.is_some());
+
+        match item_id {
+          Some(item_id) if self.pick_item_up(item_id) => ITEM_ENERGY,
+          _ => 0,
+        }
+      }
+      Action::UseItem(inventory_id, target) => {
+        if inventory_id < self.inventory.len() && self.use_item(inventory_id, target) {
+          ITEM_ENERGY
+        } else {
+          0
+        }
+      }
+      Action::LevelUp(stat) => {
+        if self.can_level_up() {
+          self.level_up(stat);
+        }
+
+        0
+      }
+      Action::CloseDoors => if self.close_doors() { ACTION_ENERGY } else { 0 },
+      Action::Descend => {
+        if self.objects[PLAYER].pos() == self.map.stairs {
+          self.next_level()?;
+        } else {
+          self.messages.add(Category::System, "There are no stairs here.", WHITE);
+        }
+
+        0
+      }
+    };
+
+    let took_turn = energy > 0;
+
+    if took_turn {
+      self.objects[PLAYER].energy -= energy;
+
+      // The world carries on until the player has the energy to act again
+      while self.objects[PLAYER].alive && self.objects[PLAYER].energy < ACTION_ENERGY {
+        self.turns += 1;
+        self.update_objects();
+      }
+    }
+
+    self.compute_fov();
+
+    Ok(took_turn)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rect::Rect;
+  use crate::replay::state_hash;
+  use crate::status::Status;
+
+  fn new_game(seed: u32) -> Game {
+    let content = Content::load().expect("The data files should load");
+    Game::new(seed, Generator::default(), Rc::new(content)).unwrap()
+  }
+
+  static DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+  fn play(seed: u32) -> Game {
+    let mut game = new_game(seed);
+
+    for step in 0..20 {
+      let (dx, dy) = DIRECTIONS[step % DIRECTIONS.len()];
+      game.perform(Action::Move(dx, dy)).unwrap();
+    }
+
+    game
+  }
+
+  #[test]
+  fn runs_without_a_window() {
+    let mut game = new_game(1);
+    let start = game.objects[PLAYER].pos();
+
+    let moved = DIRECTIONS.iter().any(|&(dx, dy)| game.perform(Action::Move(dx, dy)).unwrap());
+
+    assert!(moved);
+    assert_ne!(game.objects[PLAYER].pos(), start);
+    assert!(game.turns > 0);
+  }
+
+  #[test]
+  fn same_seed_and_actions_give_the_same_game() {
+    assert_eq!(state_hash(&play(7)), state_hash(&play(7)));
+  }
+
+  #[test]
+  fn descends_only_from_the_stairs() {
+    let mut game = new_game(2);
+
+    game.perform(Action::Descend).unwrap();
+    assert_eq!(game.depth, 1);
+    assert_eq!(game.messages.iter().last().unwrap().text, "There are no stairs here.");
+
+    let (x, y) = game.map.stairs;
+    game.objects[PLAYER].set_pos(x, y);
+    game.perform(Action::Descend).unwrap();
+    assert_eq!(game.depth, 2);
+  }
+
+  #[test]
+  fn confused_player_never_attacks_themselves() {
+    let mut game = new_game(3);
+    let messages = game.messages.iter().count();
+
+    for _ in 0..100 {
+      assert_ne!(game.random_direction(), (0, 0));
+    }
+
+    game.player_move_or_attack(0, 0);
+    assert_eq!(game.messages.iter().count(), messages);
+  }
+
+  #[test]
+  fn poison_death_ends_the_other_statuses() {
+    let mut game = new_game(4);
+    game.objects[PLAYER].fighter.as_mut().unwrap().0.hp = 1;
+    game.objects[PLAYER].statuses = vec![
+      Status { effect: Effect::Poison { damage: 50 }, turns: 1 },
+      Status { effect: Effect::Slow, turns: 1 },
+    ];
+
+    game.tick_statuses(PLAYER);
+
+    assert!(!game.objects[PLAYER].alive);
+    assert!(!game.messages.iter().any(|message| message.text.contains("no longer")));
+  }
+
+  #[test]
+  fn fighters_block_the_line_of_fire() {
+    let mut game = new_game(5);
+    game.objects.truncate(1);
+    game.map.create_room(Rect::new(0, 0, 12, 6));
+    game.objects[PLAYER].set_pos(2, 2);
+
+    assert!(game.has_line_of_fire((8, 2), (2, 2)));
+
+    let blocker = game.content.monsters[0].spawn(5, 2);
+    game.objects.push(blocker);
+
+    assert!(!game.has_line_of_fire((8, 2), (2, 2)));
+  }
+
+  #[test]
+  fn monsters_see_a_blind_player() {
+    let mut game = new_game(5);
+    game.objects.truncate(1);
+    game.map.create_room(Rect::new(0, 0, 12, 6));
+    game.objects[PLAYER].set_pos(2, 2);
+    game.objects[PLAYER].statuses.push(Status { effect: Effect::Blindness, turns: 10 });
+    game.compute_fov();
+
+    let monster = game.content.monsters[0].spawn(8, 2);
+    game.objects.push(monster);
+
+    assert!(!game.fov.is_in_fov(8, 2));
+    assert!(game.sees_player(1));
+  }
 }
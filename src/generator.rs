@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bsp::BspConfig;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Generator {
+  #[default]
+  Rooms,
+  Bsp(BspConfig),
+  Caves,
+}
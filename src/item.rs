@@ -1,4 +1,38 @@
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::dice::Dice;
+use crate::status::Effect;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Targeting {
+  Tile,
+  Monster,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Item {
-  Heal,
+  Heal { amount: Dice },
+  Lightning { damage: Dice, range: i32 },
+  Fireball { damage: Dice, radius: i32 },
+  Confuse { range: i32, duration: i32 },
+  Status { effect: Effect, duration: i32 },
+  Equip,
+  Key,
+}
+
+impl Item {
+  pub fn targeting(&self) -> Option<Targeting> {
+    match self {
+      Item::Fireball { .. } => Some(Targeting::Tile),
+      Item::Confuse { .. } => Some(Targeting::Monster),
+      _ => None,
+    }
+  }
+
+  pub fn target_range(&self) -> Option<f32> {
+    match self {
+      Item::Confuse { range, .. } => Some(*range as f32),
+      _ => None,
+    }
+  }
 }
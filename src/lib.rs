@@ -0,0 +1,33 @@
+pub mod constants;
+pub mod messages;
+pub mod tile;
+pub mod map;
+pub mod object;
+pub mod game;
+pub mod rect;
+pub mod fighter;
+pub mod ai;
+pub mod item;
+pub mod death;
+pub mod color;
+pub mod line;
+pub mod fov;
+pub mod action;
+pub mod save;
+pub mod rng;
+pub mod replay;
+pub mod equipment;
+pub mod path;
+pub mod dijkstra;
+pub mod generator;
+pub mod bsp;
+pub mod cave;
+pub mod prefab;
+pub mod features;
+pub mod monster;
+pub mod loot;
+pub mod content;
+pub mod status;
+pub mod dice;
+pub mod combat;
+pub mod damage;
@@ -0,0 +1,53 @@
+// Bresenham line from one point to another, excluding the starting point
+pub struct Line {
+  x: i32,
+  y: i32,
+  dest_x: i32,
+  dest_y: i32,
+  dx: i32,
+  dy: i32,
+  step_x: i32,
+  step_y: i32,
+  err: i32,
+}
+
+impl Line {
+  pub fn new(from: (i32, i32), to: (i32, i32)) -> Self {
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+
+    Line {
+      x: from.0,
+      y: from.1,
+      dest_x: to.0,
+      dest_y: to.1,
+      dx,
+      dy,
+      step_x: if from.0 < to.0 { 1 } else { -1 },
+      step_y: if from.1 < to.1 { 1 } else { -1 },
+      err: dx + dy,
+    }
+  }
+}
+
+impl Iterator for Line {
+  type Item = (i32, i32);
+
+  fn next(&mut self) -> Option<(i32, i32)> {
+    if (self.x, self.y) == (self.dest_x, self.dest_y) { return None; }
+
+    let e2 = 2 * self.err;
+
+    if e2 >= self.dy {
+      self.err += self.dy;
+      self.x += self.step_x;
+    }
+
+    if e2 <= self.dx {
+      self.err += self.dx;
+      self.y += self.step_y;
+    }
+
+    Some((self.x, self.y))
+  }
+}
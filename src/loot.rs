@@ -0,0 +1,293 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::content;
+use crate::damage::Resistances;
+use crate::dice::Dice;
+use crate::equipment::{Equipment, Slot};
+use crate::item::Item;
+use crate::object::Object;
+use crate::status::Effect;
+
+pub static ITEM_FILE: &str = "data/items.ron";
+pub static LOOT_FILE: &str = "data/loot.ron";
+
+// Items scattered around each level
+pub static FLOOR_TABLE: &str = "floor";
+// Items placed by prefabs such as treasure vaults
+pub static TREASURE_TABLE: &str = "treasure";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bonuses {
+  pub slot: Slot,
+  #[serde(default)]
+  pub damage: Option<Dice>,
+  #[serde(default)]
+  pub power_bonus: i32,
+  #[serde(default)]
+  pub defense_bonus: i32,
+  #[serde(default)]
+  pub max_hp_bonus: i32,
+  #[serde(default)]
+  pub resistances: Resistances,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemTemplate {
+  pub name: String,
+  pub glyph: char,
+  pub color: Color,
+  pub effect: Item,
+  #[serde(default)]
+  pub equipment: Option<Bonuses>,
+}
+
+impl ItemTemplate {
+  fn validate(&self) -> Result<(), String> {
+    if self.name.is_empty() {
+      return Err("the name can't be empty".into());
+    }
+
+    // Dice have to be sure to roll above 0, not just able to
+    let positive = match self.effect {
+      Item::Heal { amount } => amount.min() > 0,
+      Item::Lightning { damage, range } => damage.min() > 0 && range > 0,
+      Item::Fireball { damage, radius } => damage.min() > 0 && radius > 0,
+      Item::Confuse { range, duration } => range > 0 && duration > 0,
+      Item::Status { effect, duration } => duration > 0 && match effect {
+        Effect::Poison { damage } => damage > 0,
+        Effect::Regeneration { amount } => amount > 0,
+        Effect::Strength { bonus } => bonus > 0,
+        Effect::Haste | Effect::Slow | Effect::Confusion | Effect::Blindness => true,
+      },
+      Item::Equip | Item::Key => true,
+    };
+
+    if !positive {
+      return Err("the effect's parameters must be greater than 0".into());
+    }
+
+    if let Some(bonuses) = self.equipment {
+      bonuses.resistances.validate()?;
+    }
+
+    match (self.effect, self.equipment) {
+      (Item::Equip, None) => Err("equippable items need `equipment`".into()),
+      (Item::Equip, Some(_)) | (_, None) => Ok(()),
+      (_, Some(_)) => Err("only items with the `Equip` effect can have `equipment`".into()),
+    }
+  }
+
+  pub fn spawn(&self, x: i32, y: i32) -> Object {
+    let mut object = Object::new(x, y, self.glyph, self.color, &self.name, false);
+    object.item = Some(self.effect);
+
+    if let Some(bonuses) = self.equipment {
+      object.equipment = Some(Equipment {
+        slot: bonuses.slot,
+        equipped: false,
+        damage: bonuses.damage,
+        power_bonus: bonuses.power_bonus,
+        defense_bonus: bonuses.defense_bonus,
+        max_hp_bonus: bonuses.max_hp_bonus,
+        resistances: bonuses.resistances,
+      });
+    }
+
+    object
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LootEntry {
+  pub item: String,
+  // (minimum depth, weight) pairs
+  pub weight: Vec<(u32, i32)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LootTable {
+  pub name: String,
+  pub entries: Vec<LootEntry>,
+}
+
+#[derive(Default, Serialize)]
+pub struct Loot {
+  pub items: Vec<ItemTemplate>,
+  pub tables: Vec<LootTable>,
+}
+
+impl Loot {
+  pub fn item(&self, name: &str) -> Option<&ItemTemplate> {
+    self.items.iter().find(|item| item.name == name)
+  }
+
+  pub fn table(&self, name: &str) -> Option<&LootTable> {
+    self.tables.iter().find(|table| table.name == name)
+  }
+}
+
+pub fn parse_items(text: &str) -> Result<Vec<ItemTemplate>, String> {
+  let entries = content::parse_list::<ItemTemplate>(text, "item")?;
+  let items = &entries.values;
+
+  for (index, item) in items.iter().enumerate() {
+    let describe = |err: String| format!("{}: {}", entries.describe(index), err);
+
+    item.validate().map_err(describe)?;
+
+    if items[..index].iter().any(|other| other.name == item.name) {
+      return Err(describe(format!("another item is already named \"{}\"", item.name)));
+    }
+  }
+
+  Ok(entries.values)
+}
+
+pub fn parse_tables(text: &str, items: &[ItemTemplate]) -> Result<Vec<LootTable>, String> {
+  let entries = content::parse_list::<LootTable>(text, "loot table")?;
+  let tables = &entries.values;
+
+  for (index, table) in tables.iter().enumerate() {
+    let describe = |err: String| format!("{}: {}", entries.describe(index), err);
+
+    if tables[..index].iter().any(|other| other.name == table.name) {
+      return Err(describe(format!("another loot table is already named \"{}\"", table.name)));
+    }
+
+    for entry in &table.entries {
+      if !items.iter().any(|item| item.name == entry.item) {
+        return Err(describe(format!("there is no item named \"{}\"", entry.item)));
+      }
+
+      content::check_depth_table(&entry.weight)
+        .map_err(|err| describe(format!("\"{}\": {}", entry.item, err)))?;
+    }
+  }
+
+  for &name in &[FLOOR_TABLE, TREASURE_TABLE] {
+    if !tables.iter().any(|table| table.name == name) {
+      return Err(format!("there is no loot table named \"{}\"", name));
+    }
+  }
+
+  Ok(entries.values)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+  fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+pub fn load_loot(item_path: &Path, loot_path: &Path) -> Result<Loot, String> {
+  let items = parse_items(&read(item_path)?).map_err(|err| format!("{}: {}", item_path.display(), err))?;
+  let tables =
+    parse_tables(&read(loot_path)?, &items).map_err(|err| format!("{}: {}", loot_path.display(), err))?;
+
+  Ok(Loot { items, tables })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // An item on a line of its own with the given effect and any extra fields
+  fn item(name: &str, effect: &str, extra: &str) -> String {
+    format!(
+      "(name: \"{}\", glyph: '!', color: (r: 0, g: 0, b: 0), effect: {}{})",
+      name, effect, extra,
+    )
+  }
+
+  // Parses a list of a healing potion followed by the given item
+  fn parse_after_potion(second: &str) -> Result<Vec<ItemTemplate>, String> {
+    let potion = item("healing potion", "Heal(amount: \"1d4+2\")", "");
+    parse_items(&format!("[\n  {},\n  {},\n]", potion, second))
+  }
+
+  fn items() -> Vec<ItemTemplate> {
+    parse_after_potion(&item("sword", "Equip", ", equipment: Some((slot: RightHand, power_bonus: 3))"))
+      .unwrap()
+  }
+
+  // Parses the floor and treasure tables, followed by the given table
+  fn parse_with(table: &str) -> Result<Vec<LootTable>, String> {
+    let entries = "entries: [(item: \"healing potion\", weight: [(1, 10)])]";
+    let text = format!(
+      "[\n  (name: \"floor\", {}),\n  (name: \"treasure\", {}),\n  {}\n]",
+      entries, entries, table,
+    );
+    parse_tables(&text, &items())
+  }
+
+  #[test]
+  fn parses_items_and_tables() {
+    let items = items();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1].equipment.unwrap().power_bonus, 3);
+
+    let tables = parse_with("(name: \"armory\", entries: [(item: \"sword\", weight: [(1, 5), (4, 0)])]),")
+      .unwrap();
+    assert_eq!(tables.len(), 3);
+    assert_eq!(tables[2].entries[0].weight, vec![(1, 5), (4, 0)]);
+  }
+
+  #[test]
+  fn item_errors_name_the_item_and_where_it_is() {
+    assert_eq!(
+      parse_after_potion(&item("sword", "Sharp", "")).unwrap_err(),
+      "item 2 (\"sword\"), line 3 column 71: Unexpected variant named `Sharp`in enum `Item`, \
+       expected one of `Heal`, `Lightning`, `Fireball`, `Confuse`, `Status`, `Equip`, `Key` instead",
+    );
+
+    let err = parse_after_potion(&item("sword", "Key", ", weight: 3")).unwrap_err();
+    assert!(err.starts_with("item 2 (\"sword\"), line 3 column 77: Unexpected field named `weight`"));
+  }
+
+  #[test]
+  fn rejects_invalid_items() {
+    assert_eq!(
+      parse_after_potion(&item("tonic", "Heal(amount: 0)", "")).unwrap_err(),
+      "item 2 (\"tonic\"): the effect's parameters must be greater than 0",
+    );
+    assert_eq!(
+      parse_after_potion(&item("sword", "Equip", "")).unwrap_err(),
+      "item 2 (\"sword\"): equippable items need `equipment`",
+    );
+    assert_eq!(
+      parse_after_potion(&item("healing potion", "Key", "")).unwrap_err(),
+      "item 2 (\"healing potion\"): another item is already named \"healing potion\"",
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_tables() {
+    assert_eq!(
+      parse_with("(name: \"armory\", entries: 3),").unwrap_err(),
+      "loot table 3 (\"armory\"), line 4 column 29: Expected opening `[`",
+    );
+    assert_eq!(
+      parse_with("(name: \"armory\", entries: [(item: \"axe\", weight: [(1, 5)])]),").unwrap_err(),
+      "loot table 3 (\"armory\"): there is no item named \"axe\"",
+    );
+    assert_eq!(
+      parse_with("(name: \"armory\", entries: [(item: \"sword\", weight: [(4, 5), (1, 5)])]),")
+        .unwrap_err(),
+      "loot table 3 (\"armory\"): \"sword\": weights must be listed in order of increasing depth",
+    );
+    assert_eq!(
+      parse_with("(name: \"floor\", entries: []),").unwrap_err(),
+      "loot table 3 (\"floor\"): another loot table is already named \"floor\"",
+    );
+    assert_eq!(
+      parse_tables("[]", &items()).unwrap_err(),
+      "there is no loot table named \"floor\"",
+    );
+  }
+}
@@ -1,18 +1,83 @@
-mod constants;
+use std::rc::Rc;
+
+use roguelike::content::Content;
+use roguelike::replay;
+
+mod options;
+#[cfg(feature = "tcod")]
 mod render;
-mod messages;
-mod tile;
-mod map;
-mod object;
-mod game;
-mod rect;
-mod fighter;
-mod ai;
+#[cfg(feature = "tcod")]
 mod tcod;
+#[cfg(feature = "tcod")]
 mod app;
-mod item;
-mod death;
+#[cfg(feature = "tcod")]
+mod targeting;
+
+// Without the tcod frontend, only headless replays can be run
+#[cfg(not(feature = "tcod"))]
+mod app {
+  use std::rc::Rc;
+
+  use crate::options::Options;
+  use roguelike::content::Content;
+  use roguelike::replay::Replay;
+
+  static NO_WINDOW: &str = "This build has no window. Rebuild with `--features tcod` to play.";
+
+  pub fn play(_options: &Options, _content: Rc<Content>) {
+    eprintln!("{}", NO_WINDOW);
+    std::process::exit(1);
+  }
+
+  pub fn watch_replay(_replay: &Replay, _speed: u64, _content: Rc<Content>) {
+    eprintln!("{}", NO_WINDOW);
+    std::process::exit(1);
+  }
+}
 
 fn main() {
-  app::play();
+  let options = match options::Options::from_args() {
+    Ok(options) => options,
+    Err(err) => {
+      eprintln!("{}", err);
+      std::process::exit(1);
+    }
+  };
+
+  let content = match Content::load() {
+    Ok(content) => Rc::new(content),
+    Err(err) => {
+      eprintln!("Could not load the game data: {}", err);
+      std::process::exit(1);
+    }
+  };
+
+  if let Some(path) = &options.replay {
+    let replay = match replay::Replay::load(path) {
+      Ok(replay) => replay,
+      Err(err) => {
+        eprintln!("Could not load the replay: {}", err);
+        std::process::exit(1);
+      }
+    };
+
+    if options.headless {
+      match replay::run_headless(&replay, content) {
+        Ok(game) => println!(
+          "Replayed {} actions from seed {} over {} turns",
+          replay.actions.len(),
+          replay.seed,
+          game.turns,
+        ),
+        Err(err) => {
+          eprintln!("{}", err);
+          std::process::exit(1);
+        }
+      }
+    } else {
+      app::watch_replay(&replay, options.replay_speed, content);
+    }
+  } else {
+    app::play(&options, content);
+  }
 }
@@ -1,20 +1,81 @@
 use std::cmp;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::constants::*;
-use crate::tile::Tile;
+use crate::tile::{Door, Tile, TileKind};
 use crate::rect::Rect;
+use crate::generator::Generator;
+use crate::bsp;
+use crate::cave;
+use crate::features;
+use crate::prefab::{Cell, Marker, Prefab};
+use crate::content::Content;
 
-static ROOM_MAX_SIZE: i32 = 10;
-static ROOM_MIN_SIZE: i32 = 6;
+pub fn tile_index(x: i32, y: i32) -> usize {
+  (x + y * MAP_WIDTH) as usize
+}
+
+pub fn tile_position(index: usize) -> (i32, i32) {
+  (index as i32 % MAP_WIDTH, index as i32 / MAP_WIDTH)
+}
+
+// Cave levels are split into a grid of cells, and the open floor in each
+// cell is used in place of a room when spawning
+static SPAWN_CELL_SIZE: i32 = 16;
+static MIN_SPAWN_AREA: usize = 12;
+
+static MAX_GENERATION_ATTEMPTS: u32 = 5;
+
+static PREFAB_CHANCE: f32 = 0.25;
+
+static DOOR_CHANCE: f32 = 0.5;
+static LOCKED_DOOR_CHANCE: f32 = 0.1;
+
+// A patch of open floor that monsters and items can be placed in
+pub struct SpawnArea {
+  pub tiles: Vec<(i32, i32)>,
+  pub center: (i32, i32),
+}
+
+impl SpawnArea {
+  fn from_room(room: &Rect) -> Self {
+    let tiles = ((room.x1 + 1)..room.x2)
+      .flat_map(|x| ((room.y1 + 1)..room.y2).map(move |y| (x, y)))
+      .collect();
+
+    SpawnArea { tiles, center: room.center() }
+  }
+
+  // The floor tile closest to the middle of the area
+  fn from_tiles(tiles: Vec<(i32, i32)>) -> Self {
+    let count = tiles.len() as i32;
+    let mean_x = tiles.iter().map(|&(x, _)| x).sum::<i32>() / count;
+    let mean_y = tiles.iter().map(|&(_, y)| y).sum::<i32>() / count;
+
+    let center = *tiles
+      .iter()
+      .min_by_key(|&&(x, y)| (x - mean_x).pow(2) + (y - mean_y).pow(2))
+      .unwrap();
+
+    SpawnArea { tiles, center }
+  }
+}
 
+#[derive(Serialize, Deserialize)]
 pub struct Map {
   pub tiles: Vec<Vec<Tile>>,
   pub rooms: Vec<Rect>,
+  // Where the way down to the next level is
+  pub stairs: (i32, i32),
+  // Monsters and items placed by prefabs, only needed while the level is
+  // being populated
+  #[serde(skip)]
+  pub spawns: Vec<((i32, i32), Marker)>,
 }
 
 impl Map {
-  fn create_room(&mut self, room: Rect) {
+  pub fn create_room(&mut self, room: Rect) {
     for x in (room.x1 + 1)..room.x2 {
       for y in (room.y1 + 1)..room.y2 {
         self.tiles[x as usize][y as usize] = Tile::empty();
@@ -22,29 +83,331 @@ impl Map {
     }
   }
 
-  fn create_h_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
+  // Carves out a room, sometimes furnishing it with one of the prefabs that
+  // fits inside
+  pub fn carve_room<R: Rng>(&mut self, room: Rect, prefabs: &[Prefab], rng: &mut R) {
+    self.create_room(room);
+
+    if prefabs.is_empty() || rng.gen::<f32>() >= PREFAB_CHANCE { return; }
+
+    let (room_w, room_h) = (room.x2 - room.x1 - 1, room.y2 - room.y1 - 1);
+    let candidates: Vec<_> = prefabs
+      .iter()
+      .map(|prefab| prefab.transformed(rng))
+      .filter(|prefab| prefab.width() <= room_w && prefab.height() <= room_h)
+      .collect();
+
+    let total: u32 = candidates.iter().map(|prefab| prefab.weight).sum();
+    if total == 0 { return; }
+
+    let mut roll = rng.gen_range(0, total);
+    let prefab = candidates
+      .iter()
+      .find(|prefab| {
+        if roll < prefab.weight { return true; }
+        roll -= prefab.weight;
+        false
+      })
+      .unwrap();
+
+    let left = room.x1 + 1 + (room_w - prefab.width()) / 2;
+    let top = room.y1 + 1 + (room_h - prefab.height()) / 2;
+
+    for (dy, row) in prefab.cells.iter().enumerate() {
+      for (dx, &cell) in row.iter().enumerate() {
+        let (x, y) = (left + dx as i32, top + dy as i32);
+
+        self.tiles[x as usize][y as usize] = match cell {
+          Cell::Wall => Tile::wall(),
+          _ => Tile::empty(),
+        };
+
+        match cell {
+          Cell::Monster => self.spawns.push(((x, y), Marker::Monster)),
+          Cell::Item => self.spawns.push(((x, y), Marker::Item)),
+          _ => {}
+        }
+      }
+    }
+
+    // The player and the stairs are placed in the centre of a room
+    let (center_x, center_y) = room.center();
+    self.tiles[center_x as usize][center_y as usize] = Tile::empty();
+  }
+
+  pub fn create_h_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
     for x in cmp::min(x1, x2)..=(cmp::max(x1, x2)) {
       self.tiles[x as usize][y as usize] = Tile::empty();
     }
   }
 
-  fn create_v_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
+  pub fn create_v_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
     for y in cmp::min(y1, y2)..=(cmp::max(y1, y2)) {
       self.tiles[x as usize][y as usize] = Tile::empty();
     }
   }
 
-  pub fn new() -> Map {
-    let mut map = Map {
-      tiles: vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize],
-      rooms: vec![],
+  // Joins two regions with an L-shaped tunnel between their closest tiles
+  pub fn tunnel_between<R: Rng>(&mut self, from: &[(i32, i32)], to: &[(i32, i32)], rng: &mut R) {
+    let distance = |(x1, y1): (i32, i32), (x2, y2): (i32, i32)| (x1 - x2).pow(2) + (y1 - y2).pow(2);
+
+    let mut start = from[0];
+    let mut end = to[0];
+
+    for &a in from {
+      for &b in to {
+        if distance(a, b) < distance(start, end) {
+          start = a;
+          end = b;
+        }
+      }
+    }
+
+    let ((x1, y1), (x2, y2)) = (start, end);
+
+    if rng.gen() {
+      self.create_h_tunnel(x1, x2, y1);
+      self.create_v_tunnel(y1, y2, x2);
+    } else {
+      self.create_v_tunnel(y1, y2, x1);
+      self.create_h_tunnel(x1, x2, y2);
+    }
+  }
+
+  pub fn new<R: Rng>(generator: &Generator, content: &Content, rng: &mut R) -> Result<Map, String> {
+    let mut attempt = 1;
+
+    loop {
+      let mut map = Map {
+        tiles: vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize],
+        rooms: vec![],
+        stairs: (0, 0),
+        spawns: vec![],
+      };
+
+      match generator {
+        Generator::Rooms => map.place_random_rooms(&content.prefabs, rng),
+        Generator::Bsp(config) => bsp::generate(&mut map, config, &content.prefabs, rng),
+        Generator::Caves => cave::generate(&mut map, rng),
+      }
+
+      features::place(&mut map, rng);
+
+      map.connect_regions(rng);
+
+      match map.validate() {
+        Ok(()) => {
+          map.place_doors(rng);
+          map.stairs = map.spawn_areas().last().map_or((0, 0), |area| area.center);
+          return Ok(map);
+        }
+        Err(err) if attempt == MAX_GENERATION_ATTEMPTS => return Err(format!(
+          "Could not generate a playable map after {} attempts: {}",
+          MAX_GENERATION_ATTEMPTS,
+          err,
+        )),
+        Err(_) => attempt += 1,
+      }
+    }
+  }
+
+  pub fn start_position(&self) -> Option<(i32, i32)> {
+    self.spawn_areas().first().map(|area| area.center)
+  }
+
+  // Tunnels any floor that can't be reached from the player's start into the
+  // region the player starts in
+  fn connect_regions<R: Rng>(&mut self, rng: &mut R) {
+    let (x, y) = match self.start_position() {
+      Some(start) => start,
+      None => return,
+    };
+
+    let mut regions = self.regions();
+    let main = match regions.iter().position(|region| region.contains(&(x, y))) {
+      Some(main) => main,
+      None => return,
+    };
+    let mut reachable = regions.remove(main);
+
+    for region in regions {
+      self.tunnel_between(&region, &reachable, rng);
+      reachable.extend(region);
+    }
+  }
+
+  // Checks that a level is playable: there is somewhere for the player to
+  // start, and every floor tile can be reached from there
+  pub fn validate(&self) -> Result<(), String> {
+    let (x, y) = self.start_position().ok_or("The map has no rooms or open areas to start in")?;
+
+    if self.tiles[x as usize][y as usize].blocked() {
+      return Err(format!("The player's start at ({}, {}) is inside a wall", x, y));
+    }
+
+    let regions = self.regions();
+    let floor: usize = regions.iter().map(|region| region.len()).sum();
+    let reachable = regions
+      .iter()
+      .find(|region| region.contains(&(x, y)))
+      .map_or(0, |region| region.len());
+
+    if reachable < floor {
+      return Err(format!(
+        "{} floor tiles can't be reached from the player's start at ({}, {})",
+        floor - reachable,
+        x,
+        y,
+      ));
+    }
+
+    Ok(())
+  }
+
+  // Collects the tiles orthogonally connected to (x, y) that pass the check
+  fn flood<F: Fn(&Tile) -> bool>(&self, (x, y): (i32, i32), seen: &mut [bool], passable: &F) -> Vec<(i32, i32)> {
+    seen[tile_index(x, y)] = true;
+    let mut region = vec![];
+    let mut stack = vec![(x, y)];
+
+    while let Some((x, y)) = stack.pop() {
+      region.push((x, y));
+
+      for &(dx, dy) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let (nx, ny) = (x + dx, y + dy);
+
+        if nx < 0 || ny < 0 || nx >= MAP_WIDTH || ny >= MAP_HEIGHT { continue; }
+        if !passable(&self.tiles[nx as usize][ny as usize]) || seen[tile_index(nx, ny)] { continue; }
+
+        seen[tile_index(nx, ny)] = true;
+        stack.push((nx, ny));
+      }
+    }
+
+    region
+  }
+
+  // Groups of orthogonally connected open tiles. Doors count as open, since
+  // even locked ones have a key somewhere on the level, but tiles that hurt
+  // don't, so areas only reachable across lava get a tunnel of their own.
+  pub fn regions(&self) -> Vec<Vec<(i32, i32)>> {
+    let passable = |tile: &Tile| (!tile.blocked() && tile.kind.damage() == 0) || tile.door().is_some();
+    let mut seen = vec![false; (MAP_WIDTH * MAP_HEIGHT) as usize];
+    let mut regions = vec![];
+
+    for x in 0..MAP_WIDTH {
+      for y in 0..MAP_HEIGHT {
+        if !passable(&self.tiles[x as usize][y as usize]) || seen[tile_index(x, y)] { continue; }
+
+        regions.push(self.flood((x, y), &mut seen, &passable));
+      }
+    }
+
+    regions
+  }
+
+  // A gap in a room's wall with wall either side of it and floor in front and
+  // behind, where a tunnel passes through
+  fn is_doorway(&self, x: i32, y: i32, horizontal_wall: bool) -> bool {
+    if x <= 0 || y <= 0 || x >= MAP_WIDTH - 1 || y >= MAP_HEIGHT - 1 { return false; }
+
+    let tile = |dx: i32, dy: i32| self.tiles[(x + dx) as usize][(y + dy) as usize];
+    if tile(0, 0).kind != TileKind::Floor { return false; }
+
+    let (along, across) = if horizontal_wall { ((1, 0), (0, 1)) } else { ((0, 1), (1, 0)) };
+
+    tile(along.0, along.1).blocked()
+      && tile(-along.0, -along.1).blocked()
+      && !tile(across.0, across.1).blocked()
+      && !tile(-across.0, -across.1).blocked()
+  }
+
+  // Puts doors in some of the gaps where tunnels meet rooms. Each locked door
+  // gets a key that can be reached without going through any locked door.
+  fn place_doors<R: Rng>(&mut self, rng: &mut R) {
+    let mut gaps = vec![];
+
+    for room in &self.rooms {
+      for x in (room.x1 + 1)..room.x2 {
+        gaps.push((x, room.y1, true));
+        gaps.push((x, room.y2, true));
+      }
+
+      for y in (room.y1 + 1)..room.y2 {
+        gaps.push((room.x1, y, false));
+        gaps.push((room.x2, y, false));
+      }
+    }
+
+    let mut locked_doors = 0;
+
+    for (x, y, horizontal_wall) in gaps {
+      if !self.is_doorway(x, y, horizontal_wall) || rng.gen::<f32>() >= DOOR_CHANCE { continue; }
+
+      let door = if rng.gen::<f32>() < LOCKED_DOOR_CHANCE {
+        locked_doors += 1;
+        Door::Locked
+      } else {
+        Door::Closed
+      };
+
+      self.tiles[x as usize][y as usize] = Tile::new(TileKind::Door(door));
+    }
+
+    if locked_doors == 0 { return; }
+
+    let start = match self.start_position() {
+      Some(start) => start,
+      None => return,
     };
+    let mut seen = vec![false; (MAP_WIDTH * MAP_HEIGHT) as usize];
+    let reachable: Vec<_> = self
+      .flood(start, &mut seen, &Tile::is_safe)
+      .into_iter()
+      .filter(|&(x, y)| (x, y) != start && !self.tiles[x as usize][y as usize].blocked())
+      .collect();
+
+    if reachable.is_empty() { return; }
+
+    for _ in 0..locked_doors {
+      let pos = reachable[rng.gen_range(0, reachable.len())];
+      self.spawns.push((pos, Marker::Key));
+    }
+  }
+
+  // Where the player starts, monsters and items are placed, and the stairs go.
+  // The player starts in the first area and the stairs are in the last.
+  pub fn spawn_areas(&self) -> Vec<SpawnArea> {
+    if !self.rooms.is_empty() {
+      return self.rooms.iter().map(SpawnArea::from_room).collect();
+    }
+
+    let mut areas = vec![];
+
+    for cell_x in (0..MAP_WIDTH).step_by(SPAWN_CELL_SIZE as usize) {
+      for cell_y in (0..MAP_HEIGHT).step_by(SPAWN_CELL_SIZE as usize) {
+        let tiles: Vec<_> = (cell_x..cmp::min(cell_x + SPAWN_CELL_SIZE, MAP_WIDTH))
+          .flat_map(|x| (cell_y..cmp::min(cell_y + SPAWN_CELL_SIZE, MAP_HEIGHT)).map(move |y| (x, y)))
+          .filter(|&(x, y)| self.tiles[x as usize][y as usize].is_safe())
+          .collect();
+
+        if tiles.len() >= MIN_SPAWN_AREA {
+          areas.push(SpawnArea::from_tiles(tiles));
+        }
+      }
+    }
+
+    areas
+  }
+
+  fn place_random_rooms<R: Rng>(&mut self, prefabs: &[Prefab], rng: &mut R) {
+    let map = self;
 
     for _ in 0..MAX_ROOMS {
-      let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-      let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-      let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-      let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
+      let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+      let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+      let x = rng.gen_range(0, MAP_WIDTH - w);
+      let y = rng.gen_range(0, MAP_HEIGHT - h);
 
       let new_room = Rect::new(x, y, w, h);
 
@@ -52,14 +415,14 @@ impl Map {
 
       if failed { continue; }
 
-      map.create_room(new_room);
+      map.carve_room(new_room, prefabs, rng);
 
       let (new_x, new_y) = new_room.center();
 
       if !map.rooms.is_empty() {
         let (prev_x, prev_y) = map.rooms.last().unwrap().center();
 
-        if rand::random() {
+        if rng.gen() {
           map.create_h_tunnel(prev_x, new_x, prev_y);
           map.create_v_tunnel(prev_y, new_y, new_x);
         } else {
@@ -70,7 +433,42 @@ impl Map {
 
       map.rooms.push(new_room);
     }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bsp::BspConfig;
+  use crate::rng::seeded_rng;
+
+  static SEEDS: u32 = 300;
+
+  fn check(generator: Generator) {
+    let content = Content::load().unwrap();
+
+    for seed in 0..SEEDS {
+      let map = Map::new(&generator, &content, &mut seeded_rng(seed))
+        .unwrap_or_else(|err| panic!("seed {}: {}", seed, err));
+
+      if let Err(err) = map.validate() {
+        panic!("seed {}: {}", seed, err);
+      }
+    }
+  }
+
+  #[test]
+  fn rooms_are_always_valid() {
+    check(Generator::Rooms);
+  }
+
+  #[test]
+  fn bsp_is_always_valid() {
+    check(Generator::Bsp(BspConfig::default()));
+  }
 
-    map
+  #[test]
+  fn caves_are_always_valid() {
+    check(Generator::Caves);
   }
 }
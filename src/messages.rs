@@ -1,19 +1,82 @@
-use tcod::colors::*;
+use std::collections::VecDeque;
+use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+
+// Older messages are dropped once the log is this long
+static MAX_MESSAGES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Category {
+  Combat,
+  Items,
+  System,
+}
+
+impl fmt::Display for Category {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Category::Combat => write!(f, "combat"),
+      Category::Items => write!(f, "items"),
+      Category::System => write!(f, "system"),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+  pub text: String,
+  pub color: Color,
+  pub category: Category,
+  // How many times in a row the message was added
+  pub count: u32,
+}
+
+impl fmt::Display for Message {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.count > 1 {
+      write!(f, "{} x{}", self.text, self.count)
+    } else {
+      write!(f, "{}", self.text)
+    }
+  }
+}
+
+#[derive(Default, Serialize, Deserialize)]
 pub struct Messages {
-  pub messages: Vec<(String, Color)>,
+  messages: VecDeque<Message>,
 }
 
 impl Messages {
   pub fn new() -> Self {
-    Self { messages: vec![] }
+    Self { messages: VecDeque::new() }
   }
 
-  pub fn add<T: Into<String>>(&mut self, message: T, color: Color) {
-    self.messages.push((message.into(), color));
+  pub fn add<T: Into<String>>(&mut self, category: Category, message: T, color: Color) {
+    let text = message.into();
+
+    if let Some(last) = self.messages.back_mut() {
+      if last.text == text && last.color == color && last.category == category {
+        last.count += 1;
+        return;
+      }
+    }
+
+    self.messages.push_back(Message { text, color, category, count: 1 });
+
+    if self.messages.len() > MAX_MESSAGES {
+      self.messages.pop_front();
+    }
   }
 
-  pub fn iter(&self) -> impl DoubleEndedIterator<Item = &(String, Color)> {
+  pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Message> {
     self.messages.iter()
   }
+
+  // Every message, or only those in the given category
+  pub fn filtered(&self, category: Option<Category>) -> impl DoubleEndedIterator<Item = &Message> {
+    self.messages.iter().filter(move |message| category.is_none_or(|category| message.category == category))
+  }
 }
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Ai;
+use crate::color::Color;
+use crate::constants::*;
+use crate::content;
+use crate::death::Death;
+use crate::damage::{DamageType, Resistances};
+use crate::dice::Dice;
+use crate::fighter::Fighter;
+use crate::object::Object;
+
+pub static MONSTER_FILE: &str = "data/monsters.ron";
+
+fn normal_speed() -> i32 {
+  NORMAL_SPEED
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterTemplate {
+  pub name: String,
+  pub glyph: char,
+  pub color: Color,
+  pub hp: i32,
+  pub defense: i32,
+  pub power: i32,
+  pub damage: Dice,
+  #[serde(default)]
+  pub damage_type: DamageType,
+  #[serde(default)]
+  pub accuracy: i32,
+  #[serde(default)]
+  pub evasion: i32,
+  #[serde(default)]
+  pub resistances: Resistances,
+  pub xp: i32,
+  #[serde(default = "normal_speed")]
+  pub speed: i32,
+  pub ai: Ai,
+  // (minimum depth, weight) pairs
+  pub spawn_weight: Vec<(u32, i32)>,
+}
+
+impl MonsterTemplate {
+  fn validate(&self) -> Result<(), String> {
+    if self.name.is_empty() {
+      return Err("the name can't be empty".into());
+    }
+
+    if self.hp <= 0 {
+      return Err("hp must be greater than 0".into());
+    }
+
+    if self.defense < 0 || self.power < 0 || self.xp < 0 {
+      return Err("defense, power and xp can't be negative".into());
+    }
+
+    if self.speed <= 0 {
+      return Err("speed must be greater than 0".into());
+    }
+
+    self.resistances.validate()?;
+
+    if let Ai::Ranged { range, keep_distance } = self.ai {
+      if range < 2 || keep_distance < 0 || keep_distance >= range {
+        return Err("a ranged ai needs a range of at least 2, and a keep_distance from 0 up to its range".into());
+      }
+    }
+
+    content::check_depth_table(&self.spawn_weight).map_err(|err| format!("spawn_weight: {}", err))
+  }
+
+  pub fn spawn(&self, x: i32, y: i32) -> Object {
+    let mut monster = Object::new(x, y, self.glyph, self.color, &self.name, true);
+
+    monster.fighter = Some((
+      Fighter {
+        base_max_hp: self.hp,
+        hp: self.hp,
+        base_defense: self.defense,
+        base_power: self.power,
+        damage: self.damage,
+        damage_type: self.damage_type,
+        accuracy: self.accuracy,
+        evasion: self.evasion,
+        resistances: self.resistances,
+        xp: self.xp,
+      },
+      Death::Monster,
+    ));
+    monster.ai = Some(self.ai.clone());
+    monster.speed = self.speed;
+    monster.alive = true;
+
+    monster
+  }
+}
+
+pub fn parse_monsters(text: &str) -> Result<Vec<MonsterTemplate>, String> {
+  let entries = content::parse_list::<MonsterTemplate>(text, "monster")?;
+  let monsters = &entries.values;
+
+  for (index, monster) in monsters.iter().enumerate() {
+    let describe = |err: String| format!("{}: {}", entries.describe(index), err);
+
+    monster.validate().map_err(describe)?;
+
+    if monsters[..index].iter().any(|other| other.name == monster.name) {
+      return Err(describe(format!("another monster is already named \"{}\"", monster.name)));
+    }
+  }
+
+  Ok(entries.values)
+}
+
+pub fn load_monsters(path: &Path) -> Result<Vec<MonsterTemplate>, String> {
+  let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+  parse_monsters(&text).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A monster on a line of its own, with hp given as written and any extra
+  // fields appended
+  fn monster(name: &str, hp: &str, extra: &str) -> String {
+    format!(
+      "(name: \"{}\", glyph: 'o', color: (r: 0, g: 0, b: 0), hp: {}, defense: 0, power: 0, \
+       damage: \"1d4\", xp: 10, ai: Basic, spawn_weight: [(1, 10)]{})",
+      name, hp, extra,
+    )
+  }
+
+  // Parses a list of an orc followed by the given monster
+  fn parse_after_orc(second: &str) -> Result<Vec<MonsterTemplate>, String> {
+    parse_monsters(&format!("[\n  {},\n  {},\n]", monster("orc", "10", ""), second))
+  }
+
+  #[test]
+  fn parses_monsters() {
+    let monsters = parse_after_orc(&monster("troll", "16", ", speed: 50")).unwrap();
+
+    assert_eq!(monsters.len(), 2);
+    assert_eq!(monsters[1].name, "troll");
+    assert_eq!(monsters[1].hp, 16);
+    assert_eq!(monsters[1].speed, 50);
+    assert_eq!(monsters[0].speed, NORMAL_SPEED);
+  }
+
+  #[test]
+  fn errors_name_the_monster_and_where_it_is() {
+    assert_eq!(
+      parse_after_orc(&monster("troll", "\"lots\"", "")).unwrap_err(),
+      "monster 2 (\"troll\"), line 3 column 62: Expected integer",
+    );
+
+    let err = parse_after_orc(&monster("troll", "16", ", size: 3")).unwrap_err();
+    assert!(err.starts_with("monster 2 (\"troll\"), line 3 column 151: Unexpected field named `size`"));
+
+    // Without a name to read, the monster is only numbered
+    assert_eq!(
+      parse_after_orc("5").unwrap_err(),
+      "monster 2, line 3 column 3: Expected opening `(` for struct `MonsterTemplate`",
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_monsters() {
+    assert_eq!(
+      parse_after_orc(&monster("troll", "0", "")).unwrap_err(),
+      "monster 2 (\"troll\"): hp must be greater than 0",
+    );
+    assert_eq!(
+      parse_after_orc(&monster("orc", "16", "")).unwrap_err(),
+      "monster 2 (\"orc\"): another monster is already named \"orc\"",
+    );
+  }
+}
@@ -1,13 +1,17 @@
-use tcod::colors::*;
-use tcod::console::*;
+use serde::{Deserialize, Serialize};
 
+use crate::constants::*;
+
+use crate::color::Color;
 use crate::fighter::Fighter;
 use crate::ai::Ai;
 use crate::item::Item;
+use crate::equipment::Equipment;
+use crate::status::{Effect, Status};
 
 use crate::death::Death;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Object {
   pub x: i32,
   pub y: i32,
@@ -16,14 +20,20 @@ pub struct Object {
   pub name: String,
   pub blocks: bool,
   pub alive: bool,
+  pub always_visible: bool,
+  pub level: i32,
   pub fighter: Option<(Fighter, Death)>,
   pub ai: Option<Ai>,
   pub item: Option<Item>,
+  pub equipment: Option<Equipment>,
+  pub statuses: Vec<Status>,
+  pub speed: i32,
+  pub energy: i32,
 }
 
 impl Object {
   pub fn new(x: i32, y: i32, char: char, color: Color, name: &str, blocks: bool) -> Self {
-    Object { x, y, char, color, blocks, name: name.into(), alive: false, fighter: None, ai: None, item: None }
+    Object { x, y, char, color, blocks, name: name.into(), alive: false, always_visible: false, level: 1, fighter: None, ai: None, item: None, equipment: None, statuses: vec![], speed: NORMAL_SPEED, energy: 0 }
   }
 
   pub fn set_pos(&mut self, x: i32, y: i32) {
@@ -35,6 +45,10 @@ impl Object {
     (self.x, self.y)
   }
 
+  pub fn distance(&self, x: i32, y: i32) -> f32 {
+    (((x - self.x).pow(2) + (y - self.y).pow(2)) as f32).sqrt()
+  }
+
   pub fn distance_to(&self, other: &Object) -> f32 {
     let dx = other.x - self.x;
     let dy = other.y - self.y;
@@ -57,15 +71,25 @@ impl Object {
     true
   }
 
-  pub fn heal(&mut self, amount: i32) {
+  pub fn heal(&mut self, amount: i32, max_hp: i32) {
     if let Some((fighter, _)) = self.fighter.as_mut() {
       let hp = fighter.hp + amount;
-      fighter.hp = if hp > fighter.max_hp { fighter.max_hp } else { hp };
+      fighter.hp = if hp > max_hp { max_hp } else { hp };
+    }
+  }
+
+  // Reapplying an effect replaces it, keeping whichever lasts longer
+  pub fn add_status(&mut self, effect: Effect, turns: i32) {
+    match self.statuses.iter_mut().find(|status| status.effect.same_kind(&effect)) {
+      Some(status) => {
+        status.effect = effect;
+        status.turns = status.turns.max(turns);
+      }
+      None => self.statuses.push(Status { effect, turns }),
     }
   }
 
-  pub fn draw(&self, con: &mut dyn Console) {
-    con.set_default_foreground(self.color);
-    con.put_char(self.x, self.y, self.char, BackgroundFlag::None);
+  pub fn has_status(&self, effect: Effect) -> bool {
+    self.statuses.iter().any(|status| status.effect.same_kind(&effect))
   }
 }
@@ -0,0 +1,123 @@
+use std::env;
+
+use roguelike::bsp::BspConfig;
+use roguelike::generator::Generator;
+
+static DEFAULT_REPLAY_SPEED: u64 = 100;
+
+pub struct Options {
+  pub seed: Option<u32>,
+  pub generator: Option<Generator>,
+  pub record: Option<String>,
+  pub replay: Option<String>,
+  pub replay_speed: u64,
+  pub headless: bool,
+}
+
+fn parse_value<T: std::str::FromStr>(
+  args: &mut impl Iterator<Item = String>,
+  flag: &str,
+) -> Result<T, String> {
+  let value = args.next().ok_or(format!("{} requires a value", flag))?;
+  value.parse().map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+impl Options {
+  pub fn from_args() -> Result<Options, String> {
+    let mut options = Options {
+      seed: None,
+      generator: None,
+      record: None,
+      replay: None,
+      replay_speed: DEFAULT_REPLAY_SPEED,
+      headless: false,
+    };
+    let mut generator_name = None;
+    let mut bsp = BspConfig::default();
+    let mut bsp_tuned = false;
+    let mut speed_set = false;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--seed" => {
+          let value = args.next().ok_or("--seed requires a value")?;
+          let seed = value.parse().map_err(|_| format!("Invalid seed: {}", value))?;
+          options.seed = Some(seed);
+        }
+        "--generator" => {
+          generator_name = Some(args.next().ok_or("--generator requires a name")?);
+        }
+        "--min-leaf" => {
+          bsp.min_leaf_size = parse_value(&mut args, "--min-leaf")?;
+          bsp_tuned = true;
+        }
+        "--max-leaf" => {
+          bsp.max_leaf_size = parse_value(&mut args, "--max-leaf")?;
+          bsp_tuned = true;
+        }
+        "--split-ratio" => {
+          bsp.split_ratio = parse_value(&mut args, "--split-ratio")?;
+          bsp_tuned = true;
+        }
+        "--record" => {
+          options.record = Some(args.next().ok_or("--record requires a file name")?);
+        }
+        "--replay" => {
+          options.replay = Some(args.next().ok_or("--replay requires a file name")?);
+        }
+        "--speed" => {
+          let value = args.next().ok_or("--speed requires a value")?;
+          options.replay_speed = value.parse().map_err(|_| format!("Invalid speed: {}", value))?;
+          speed_set = true;
+        }
+        "--headless" => options.headless = true,
+        _ => return Err(format!("Unknown argument: {}", arg)),
+      }
+    }
+
+    if bsp_tuned && generator_name.as_deref() != Some("bsp") {
+      return Err("BSP options require --generator bsp".into());
+    }
+
+    options.generator = match generator_name.as_deref() {
+      None => None,
+      Some("rooms") => Some(Generator::Rooms),
+      Some("caves") => Some(Generator::Caves),
+      Some("bsp") => {
+        bsp.validate()?;
+        Some(Generator::Bsp(bsp))
+      }
+      Some(name) => return Err(format!("Unknown generator: {}", name)),
+    };
+
+    if options.headless && options.replay.is_none() {
+      return Err("--headless can only be used with --replay".into());
+    }
+
+    if options.record.is_some() && options.replay.is_some() {
+      return Err("--record and --replay cannot be used together".into());
+    }
+
+    if options.generator.is_some() && options.replay.is_some() {
+      return Err("--generator cannot be used with --replay".into());
+    }
+
+    // A replay always starts from the seed it was recorded with
+    if options.seed.is_some() && options.replay.is_some() {
+      return Err("--seed cannot be used with --replay".into());
+    }
+
+    if speed_set && (options.replay.is_none() || options.headless) {
+      return Err("--speed can only be used when watching a replay".into());
+    }
+
+    Ok(options)
+  }
+
+  // Asking for a particular dungeon skips loading the saved game
+  #[cfg(feature = "tcod")]
+  pub fn starts_new_game(&self) -> bool {
+    self.seed.is_some() || self.generator.is_some() || self.record.is_some()
+  }
+}
@@ -0,0 +1,156 @@
+use std::cmp::{self, Ordering};
+use std::collections::BinaryHeap;
+
+use crate::constants::*;
+use crate::map::{tile_index, tile_position, Map};
+
+static STRAIGHT_COST: i32 = 10;
+static DIAGONAL_COST: i32 = 14;
+static OCCUPIED_COST: i32 = 100;
+
+pub static MAX_PATH_LENGTH: i32 = 25;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Node {
+  estimate: i32,
+  cost: i32,
+  index: usize,
+}
+
+impl Ord for Node {
+  fn cmp(&self, other: &Node) -> Ordering {
+    other.estimate.cmp(&self.estimate).then_with(|| self.cost.cmp(&other.cost))
+  }
+}
+
+impl PartialOrd for Node {
+  fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+fn heuristic(from: (i32, i32), to: (i32, i32)) -> i32 {
+  let dx = (to.0 - from.0).abs();
+  let dy = (to.1 - from.1).abs();
+
+  STRAIGHT_COST * (cmp::max(dx, dy) - cmp::min(dx, dy)) + DIAGONAL_COST * cmp::min(dx, dy)
+}
+
+// A* search over the map's tiles, allowing diagonal moves. Tiles in `occupied`
+// can be walked through at a high cost, so monsters will queue behind each
+// other or route around a crowd rather than giving up. Returns the steps after
+// `from` up to and including `to`, or None if no path of at most `max_length`
+// steps exists.
+pub fn find_path(
+  map: &Map,
+  occupied: &[(i32, i32)],
+  from: (i32, i32),
+  to: (i32, i32),
+  max_length: i32,
+) -> Option<Vec<(i32, i32)>> {
+  let size = (MAP_WIDTH * MAP_HEIGHT) as usize;
+
+  let mut extra_cost = vec![0; size];
+  for &(x, y) in occupied {
+    extra_cost[tile_index(x, y)] = OCCUPIED_COST;
+  }
+
+  let mut costs = vec![i32::MAX; size];
+  let mut steps = vec![0; size];
+  let mut came_from = vec![usize::MAX; size];
+  let mut open = BinaryHeap::new();
+
+  let start = tile_index(from.0, from.1);
+  let goal = tile_index(to.0, to.1);
+
+  costs[start] = 0;
+  open.push(Node { estimate: heuristic(from, to), cost: 0, index: start });
+
+  while let Some(Node { cost, index: current, .. }) = open.pop() {
+    if current == goal {
+      let mut path = vec![];
+      let mut node = goal;
+
+      while node != start {
+        path.push(tile_position(node));
+        node = came_from[node];
+      }
+
+      path.reverse();
+      return Some(path);
+    }
+
+    if cost > costs[current] || steps[current] >= max_length { continue; }
+
+    let (x, y) = tile_position(current);
+
+    for dx in -1..=1 {
+      for dy in -1..=1 {
+        let (nx, ny) = (x + dx, y + dy);
+
+        if (dx, dy) == (0, 0) || nx < 0 || ny < 0 || nx >= MAP_WIDTH || ny >= MAP_HEIGHT { continue; }
+        if !map.tiles[nx as usize][ny as usize].is_walkable() { continue; }
+
+        let next = tile_index(nx, ny);
+        let step_cost = if dx != 0 && dy != 0 { DIAGONAL_COST } else { STRAIGHT_COST };
+        let step_cost = step_cost * map.tiles[nx as usize][ny as usize].kind.path_cost() as i32;
+        let next_cost = cost + step_cost + if next == goal { 0 } else { extra_cost[next] };
+
+        if next_cost < costs[next] {
+          costs[next] = next_cost;
+          steps[next] = steps[current] + 1;
+          came_from[next] = current;
+          open.push(Node { estimate: next_cost + heuristic((nx, ny), to), cost: next_cost, index: next });
+        }
+      }
+    }
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rect::Rect;
+  use crate::tile::{Tile, TileKind};
+
+  // A room split down the middle by a wall of lava with one gap in it
+  fn lava_room(gap: Option<i32>) -> Map {
+    let mut map = Map {
+      tiles: vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize],
+      rooms: vec![],
+      stairs: (0, 0),
+      spawns: vec![],
+    };
+    map.create_room(Rect::new(0, 0, 10, 10));
+
+    for y in 1..10 {
+      if Some(y) != gap {
+        map.tiles[5][y as usize] = Tile::new(TileKind::Lava);
+      }
+    }
+
+    map
+  }
+
+  fn crosses_lava(map: &Map, path: &[(i32, i32)]) -> bool {
+    path.iter().any(|&(x, y)| map.tiles[x as usize][y as usize].kind == TileKind::Lava)
+  }
+
+  #[test]
+  fn goes_round_lava() {
+    let map = lava_room(Some(8));
+    let path = find_path(&map, &[], (2, 2), (8, 2), MAX_PATH_LENGTH).unwrap();
+
+    assert!(!crosses_lava(&map, &path));
+  }
+
+  #[test]
+  fn crosses_lava_when_there_is_no_way_round() {
+    let map = lava_room(None);
+    let path = find_path(&map, &[], (2, 2), (8, 2), MAX_PATH_LENGTH).unwrap();
+
+    assert!(crosses_lava(&map, &path));
+  }
+}
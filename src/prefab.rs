@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub static PREFAB_DIR: &str = "data/prefabs";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Cell {
+  Floor,
+  Wall,
+  Monster,
+  Item,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Marker {
+  Monster,
+  Item,
+  Key,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Prefab {
+  pub name: String,
+  pub weight: u32,
+  // Rows of cells, top to bottom
+  pub cells: Vec<Vec<Cell>>,
+}
+
+fn parse_cell(kind: &str) -> Option<Cell> {
+  match kind {
+    "floor" => Some(Cell::Floor),
+    "wall" => Some(Cell::Wall),
+    "monster" => Some(Cell::Monster),
+    "item" => Some(Cell::Item),
+    _ => None,
+  }
+}
+
+impl Prefab {
+  // A header of `key: value` lines, then `---`, then the rows of the prefab.
+  // Every character in the rows must be given a cell type with a `legend` line.
+  pub fn parse(text: &str) -> Result<Prefab, String> {
+    let mut name = None;
+    let mut weight = 1;
+    let mut legend = vec![];
+    let mut lines = text.lines().enumerate();
+
+    for (number, line) in &mut lines {
+      let line = line.trim_end();
+      if line == "---" { break; }
+      if line.is_empty() { continue; }
+
+      let error = |message: &str| format!("line {}: {}", number + 1, message);
+      let (key, value) = line.split_once(": ").ok_or_else(|| error("expected `key: value`"))?;
+
+      match key {
+        "name" => name = Some(value.to_string()),
+        "weight" => weight = value.parse().map_err(|_| error("the weight must be a whole number"))?,
+        "legend" => {
+          let mut chars = value.chars();
+          let symbol = chars.next().ok_or_else(|| error("the legend needs a character"))?;
+          let kind = chars.as_str().trim();
+          let cell = parse_cell(kind)
+            .ok_or_else(|| error(&format!("unknown cell type `{}`", kind)))?;
+
+          legend.push((symbol, cell));
+        }
+        _ => return Err(error(&format!("unknown key `{}`", key))),
+      }
+    }
+
+    let mut cells: Vec<Vec<Cell>> = vec![];
+
+    for (number, line) in lines {
+      let line = line.trim_end();
+      if line.is_empty() { continue; }
+
+      let row = line
+        .chars()
+        .map(|symbol| {
+          legend
+            .iter()
+            .find(|&&(s, _)| s == symbol)
+            .map(|&(_, cell)| cell)
+            .ok_or_else(|| format!("line {}: `{}` isn't in the legend", number + 1, symbol))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+      if cells.first().is_some_and(|first| first.len() != row.len()) {
+        return Err(format!("line {}: every row must be the same width", number + 1));
+      }
+
+      cells.push(row);
+    }
+
+    if cells.is_empty() {
+      return Err("the prefab has no rows".into());
+    }
+
+    Ok(Prefab { name: name.ok_or("the prefab has no name")?, weight, cells })
+  }
+
+  pub fn width(&self) -> i32 {
+    self.cells[0].len() as i32
+  }
+
+  pub fn height(&self) -> i32 {
+    self.cells.len() as i32
+  }
+
+  pub fn rotated(&self) -> Prefab {
+    let (w, h) = (self.width() as usize, self.height() as usize);
+    let cells = (0..w).map(|x| (0..h).rev().map(|y| self.cells[y][x]).collect()).collect();
+
+    Prefab { cells, ..self.clone() }
+  }
+
+  pub fn mirrored(&self) -> Prefab {
+    let cells = self.cells.iter().map(|row| row.iter().rev().cloned().collect()).collect();
+
+    Prefab { cells, ..self.clone() }
+  }
+
+  pub fn transformed<R: Rng>(&self, rng: &mut R) -> Prefab {
+    let mut prefab = if rng.gen() { self.mirrored() } else { self.clone() };
+
+    for _ in 0..rng.gen_range(0, 4) {
+      prefab = prefab.rotated();
+    }
+
+    prefab
+  }
+}
+
+pub fn load_prefabs(dir: &Path) -> Result<Vec<Prefab>, String> {
+  let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+
+  let mut paths: Vec<_> = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+    .collect();
+
+  // Sorted so every run picks from the prefabs in the same order
+  paths.sort();
+
+  let mut prefabs: Vec<Prefab> = vec![];
+
+  for path in paths {
+    let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let prefab = Prefab::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    if prefabs.iter().any(|other| other.name == prefab.name) {
+      return Err(format!("{}: another prefab is already named `{}`", path.display(), prefab.name));
+    }
+
+    prefabs.push(prefab);
+  }
+
+  Ok(prefabs)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static HEADER: &str = "name: nook\nweight: 2\nlegend: . floor\nlegend: # wall\nlegend: ! item\n---\n";
+
+  // The prefab's rows drawn the way they're written in a file
+  fn rows(prefab: &Prefab) -> Vec<String> {
+    prefab
+      .cells
+      .iter()
+      .map(|row| {
+        row
+          .iter()
+          .map(|cell| match cell {
+            Cell::Floor => '.',
+            Cell::Wall => '#',
+            Cell::Monster => 'o',
+            Cell::Item => '!',
+          })
+          .collect()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn parses_a_prefab() {
+    let prefab = Prefab::parse(&format!("{}##.\n#!.\n", HEADER)).unwrap();
+
+    assert_eq!(prefab.name, "nook");
+    assert_eq!(prefab.weight, 2);
+    assert_eq!((prefab.width(), prefab.height()), (3, 2));
+    assert_eq!(rows(&prefab), ["##.", "#!."]);
+  }
+
+  #[test]
+  fn rotates_and_mirrors() {
+    let prefab = Prefab::parse(&format!("{}##.\n#!.\n", HEADER)).unwrap();
+
+    assert_eq!(rows(&prefab.rotated()), ["##", "!#", ".."]);
+    assert_eq!(rows(&prefab.mirrored()), [".##", ".!#"]);
+    assert_eq!(rows(&prefab.rotated().rotated().rotated().rotated()), rows(&prefab));
+  }
+
+  #[test]
+  fn rejects_invalid_prefabs() {
+    let error = |text: &str| Prefab::parse(text).unwrap_err();
+
+    assert_eq!(error(&format!("{}##.\n#x.\n", HEADER)), "line 8: `x` isn't in the legend");
+    assert_eq!(error(&format!("{}##.\n#!\n", HEADER)), "line 8: every row must be the same width");
+    assert_eq!(error("name: nook\nsize: 3\n---\n"), "line 2: unknown key `size`");
+    assert_eq!(error("name: nook\nlegend: . lava\n---\n"), "line 2: unknown cell type `lava`");
+    assert_eq!(error(HEADER), "the prefab has no rows");
+    assert_eq!(error("legend: . floor\n---\n.\n"), "the prefab has no name");
+  }
+}
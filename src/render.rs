@@ -1,9 +1,13 @@
 use ::tcod::colors::*;
 use ::tcod::console::*;
 
-use crate::constants::*;
-use crate::tcod::Tcod;
-use crate::game::Game;
+use roguelike::constants::*;
+use roguelike::game::Game;
+use roguelike::messages::Category;
+use roguelike::object::Object;
+use roguelike::dijkstra::Goal;
+
+use crate::tcod::{Tcod, ToTcod};
 
 static PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
 
@@ -13,10 +17,15 @@ static MSG_X: i32 = BAR_WIDTH + 2;
 static MSG_WIDTH: i32 = SCREEN_WIDTH - MSG_X;
 static MSG_HEIGHT: i32 = PANEL_HEIGHT - 1;
 
-static COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
-static COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
-static COLOR_DARK_GROUND: Color = Color { r: 50, g: 50, b: 150, };
-static COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50, };
+static LOG_X: i32 = 1;
+static LOG_WIDTH: i32 = SCREEN_WIDTH - 2;
+static LOG_TOP: i32 = 2;
+pub static LOG_PAGE: usize = (SCREEN_HEIGHT - LOG_TOP) as usize;
+
+static COLOR_OVERLAY_NEAR: Color = Color { r: 220, g: 40, b: 40 };
+static COLOR_OVERLAY_FAR: Color = Color { r: 40, g: 40, b: 220 };
+static COLOR_VALID_TARGET: Color = Color { r: 80, g: 220, b: 80 };
+static COLOR_INVALID_TARGET: Color = Color { r: 180, g: 40, b: 40 };
 
 #[allow(clippy::too_many_arguments)]
 fn render_bar(
@@ -50,43 +59,149 @@ fn render_bar(
   );
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overlay {
+  Chase(Goal),
+  Flee(Goal),
+}
+
+impl Overlay {
+  // The goal and direction of the Dijkstra map shown
+  pub fn map(self) -> (Goal, bool) {
+    match self {
+      Overlay::Chase(goal) => (goal, false),
+      Overlay::Flee(goal) => (goal, true),
+    }
+  }
+}
+
+pub struct Cursor {
+  pub x: i32,
+  pub y: i32,
+  pub valid: bool,
+}
+
+pub fn names_at(game: &Game, x: i32, y: i32) -> String {
+  let names = game.objects
+    .iter()
+    .filter(|obj| obj.pos() == (x, y) && game.fov.is_in_fov(obj.x, obj.y))
+    .map(|obj| obj.name.clone())
+    .collect::<Vec<_>>();
+
+  names.join(", ")
+}
+
+fn draw_object(con: &mut dyn Console, object: &Object) {
+  con.set_default_foreground(object.color.to_tcod());
+  con.put_char(object.x, object.y, object.char, BackgroundFlag::None);
+}
+
 fn render_objects(tcod: &mut Tcod, game: &Game) {
   let mut to_draw: Vec<_> = game.objects
     .iter()
-    .filter(|o| tcod.fov.is_in_fov(o.x, o.y))
+    .filter(|o| {
+      game.fov.is_in_fov(o.x, o.y)
+        || (o.always_visible && game.map.tiles[o.x as usize][o.y as usize].explored)
+    })
     .collect();
 
   to_draw.sort_by(|o1, o2| { o1.blocks.cmp(&o2.blocks)});
 
   for object in &to_draw {
-    if tcod.fov.is_in_fov(object.x, object.y) {
-      object.draw(&mut tcod.con);
+    draw_object(&mut tcod.con, object);
+  }
+}
+
+fn render_projectiles(tcod: &mut Tcod, game: &Game) {
+  for projectile in &game.projectiles {
+    let (first, last) = match (projectile.path.first(), projectile.path.last()) {
+      (Some(&first), Some(&last)) => (first, last),
+      _ => continue,
+    };
+
+    let glyph = match (last.0 - first.0, last.1 - first.1) {
+      (_, 0) => '-',
+      (0, _) => '|',
+      (dx, dy) if (dx > 0) == (dy > 0) => '\\',
+      _ => '/',
+    };
+
+    tcod.con.set_default_foreground(projectile.color.to_tcod());
+    for &(x, y) in &projectile.path {
+      if game.fov.is_in_fov(x, y) {
+        tcod.con.put_char(x, y, glyph, BackgroundFlag::None);
+      }
     }
   }
 }
 
-fn render_map(tcod: &mut Tcod, game: &mut Game) {
+fn render_map(tcod: &mut Tcod, game: &Game) {
   for y in 0..MAP_HEIGHT {
     for x in 0..MAP_WIDTH {
-      let visible = tcod.fov.is_in_fov(x, y);
+      let visible = game.fov.is_in_fov(x, y);
+
+      if !game.map.tiles[x as usize][y as usize].explored { continue; }
+
+      let kind = game.map.tiles[x as usize][y as usize].kind;
+      let background: Color = kind.background(visible).to_tcod();
 
-      let explored = &mut game.map.tiles[x as usize][y as usize].explored;
+      match kind.glyph() {
+        Some((glyph, color)) => {
+          let foreground: Color = color.to_tcod();
+          let foreground = if visible { foreground } else { foreground * 0.5 };
 
-      if visible { *explored = true; }
-      if !*explored { continue; }
+          tcod.con.put_char_ex(x, y, glyph, foreground, background);
+        }
+        None => tcod.con.set_char_background(x, y, background, BackgroundFlag::Set),
+      }
+    }
+  }
+}
 
-      let wall = game.map.tiles[x as usize][y as usize].block_sight;
+// Shows the game's own cached map, which the frontend refreshes before
+// rendering
+fn render_overlay(tcod: &mut Tcod, game: &Game, overlay: Overlay) {
+  let (goal, flee) = overlay.map();
+  let dijkstra_map = match game.dijkstra_map(goal, flee) {
+    Some(dijkstra_map) => dijkstra_map,
+    None => return,
+  };
 
-      let color = match (visible, wall) {
-        (false, true) => COLOR_DARK_WALL,
-        (false, false) => COLOR_DARK_GROUND,
-        (true, true) => COLOR_LIGHT_WALL,
-        (true, false) => COLOR_LIGHT_GROUND,
-      };
+  let mut tiles = vec![];
 
-      tcod.con.set_char_background(x, y, color, BackgroundFlag::Set);
+  for y in 0..MAP_HEIGHT {
+    for x in 0..MAP_WIDTH {
+      let value = dijkstra_map.value(x, y);
+
+      if game.map.tiles[x as usize][y as usize].explored && value.is_finite() {
+        tiles.push((x, y, value));
+      }
     }
   }
+
+  let min = tiles.iter().map(|t| t.2).fold(f32::INFINITY, f32::min);
+  let max = tiles.iter().map(|t| t.2).fold(f32::NEG_INFINITY, f32::max);
+
+  for (x, y, value) in tiles {
+    let coefficient = if max > min { (value - min) / (max - min) } else { 0.0 };
+    let color = lerp(COLOR_OVERLAY_NEAR, COLOR_OVERLAY_FAR, coefficient);
+
+    tcod.con.set_char_background(x, y, color, BackgroundFlag::Set);
+  }
+
+  tcod.panel.set_default_foreground(LIGHT_GREY);
+  tcod.panel.print_ex(
+    1,
+    5,
+    BackgroundFlag::None,
+    TextAlignment::Left,
+    format!("Overlay: {:?}", overlay),
+  );
+}
+
+fn render_cursor(tcod: &mut Tcod, cursor: &Cursor) {
+  let color = if cursor.valid { COLOR_VALID_TARGET } else { COLOR_INVALID_TARGET };
+  tcod.con.set_char_background(cursor.x, cursor.y, color, BackgroundFlag::Set);
 }
 
 fn render_mouselook(tcod: &mut Tcod, names_under_mouse: String) {
@@ -100,21 +215,105 @@ fn render_mouselook(tcod: &mut Tcod, names_under_mouse: String) {
   );
 }
 
+fn render_levels(tcod: &mut Tcod, game: &Game) {
+  tcod.panel.set_default_foreground(WHITE);
+  tcod.panel.print_ex(
+    1,
+    3,
+    BackgroundFlag::None,
+    TextAlignment::Left,
+    format!("Dungeon level: {}", game.depth),
+  );
+  tcod.panel.print_ex(
+    1,
+    4,
+    BackgroundFlag::None,
+    TextAlignment::Left,
+    format!("Character level: {}", game.objects[PLAYER].level),
+  );
+}
+
+// The tags sit under the messages, where the row is free of the seed and
+// the overlay label and wide enough for every effect at once
+fn render_statuses(tcod: &mut Tcod, game: &Game) {
+  let mut x = MSG_X;
+  for status in &game.objects[PLAYER].statuses {
+    let tag = status.effect.tag();
+    if x + tag.len() as i32 > SCREEN_WIDTH { break; }
+
+    tcod.panel.set_default_foreground(status.effect.color().to_tcod());
+    tcod.panel.print_ex(
+      x,
+      PANEL_HEIGHT - 1,
+      BackgroundFlag::None,
+      TextAlignment::Left,
+      tag,
+    );
+    x += tag.len() as i32 + 1;
+  }
+}
+
+fn render_seed(tcod: &mut Tcod, seed: u32) {
+  tcod.panel.set_default_foreground(LIGHT_GREY);
+  tcod.panel.print_ex(
+    1,
+    PANEL_HEIGHT - 1,
+    BackgroundFlag::None,
+    TextAlignment::Left,
+    format!("Seed: {}", seed),
+  );
+}
+
 fn render_messages(tcod: &mut Tcod, game: &Game) {
   let mut y = MSG_HEIGHT;
-  for &(ref msg, color) in game.messages.iter().rev() {
-    let height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+  for message in game.messages.iter().rev() {
+    let text = message.to_string();
+    let height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, &text);
     y -= height;
 
     if y < 0 {
       break;
     }
 
-    tcod.panel.set_default_foreground(color);
-    tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+    tcod.panel.set_default_foreground(message.color.to_tcod());
+    tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, &text);
   }
 }
 
+// Fills the screen with the message history, newest at the bottom. `scroll`
+// is how many lines up from the newest to start, since long messages wrap.
+// Returns the furthest the log can be scrolled.
+pub fn render_message_log(tcod: &mut Tcod, game: &Game, filter: Option<Category>, scroll: usize) -> usize {
+  tcod.root.set_default_background(BLACK);
+  tcod.root.clear();
+
+  let shown = filter.map_or("all".to_string(), |category| category.to_string());
+  let header = format!(
+    "Message log ({}). Tab to filter, PageUp/PageDown to scroll, Escape to close.",
+    shown,
+  );
+  tcod.root.set_default_foreground(WHITE);
+  tcod.root.print_rect(LOG_X, 0, LOG_WIDTH, 0, header);
+
+  let mut y = SCREEN_HEIGHT + scroll as i32;
+  for message in game.messages.filtered(filter).rev() {
+    let text = message.to_string();
+    y -= tcod.root.get_height_rect(LOG_X, 0, LOG_WIDTH, 0, &text);
+
+    // Lines below the screen are cut off, but a message that would run into
+    // the header is left out
+    if y < LOG_TOP || y >= SCREEN_HEIGHT { continue; }
+
+    tcod.root.set_default_foreground(message.color.to_tcod());
+    tcod.root.print_rect(LOG_X, y, LOG_WIDTH, 0, &text);
+  }
+
+  tcod.root.flush();
+
+  let lines = (SCREEN_HEIGHT + scroll as i32 - y) as usize;
+  lines.saturating_sub(LOG_PAGE)
+}
+
 pub fn render_menu<T: AsRef<str>>(tcod: &mut Tcod, header: &str, options: &[T], width: i32) {
   assert!(
     options.len() <= 26,
@@ -156,11 +355,23 @@ pub fn render_menu<T: AsRef<str>>(tcod: &mut Tcod, header: &str, options: &[T],
   tcod.root.flush();
 }
 
-pub fn render_game(tcod: &mut Tcod, game: &mut Game, names_under_mouse: String) {
+pub fn render_game(tcod: &mut Tcod, game: &Game, names_under_mouse: String, cursor: Option<&Cursor>) {
   tcod.con.clear();
 
-  render_objects(tcod, game);
   render_map(tcod, game);
+  render_objects(tcod, game);
+  render_projectiles(tcod, game);
+
+  tcod.panel.set_default_background(BLACK);
+  tcod.panel.clear();
+
+  if let Some(overlay) = tcod.overlay {
+    render_overlay(tcod, game, overlay);
+  }
+
+  if let Some(cursor) = cursor {
+    render_cursor(tcod, cursor);
+  }
 
   blit(
     &tcod.con,
@@ -172,10 +383,8 @@ pub fn render_game(tcod: &mut Tcod, game: &mut Game, names_under_mouse: String)
     1.0,
   );
 
-  tcod.panel.set_default_background(BLACK);
-  tcod.panel.clear();
-
-  let (hp, max_hp) = game.objects[PLAYER].fighter.map_or((0, 0), |(f, _)| (f.hp, f.max_hp));
+  let hp = game.objects[PLAYER].fighter.map_or(0, |(f, _)| f.hp);
+  let max_hp = game.max_hp(PLAYER);
   render_bar(
     &mut tcod.panel,
     1,
@@ -187,7 +396,23 @@ pub fn render_game(tcod: &mut Tcod, game: &mut Game, names_under_mouse: String)
     LIGHT_RED,
     DARK_RED,
   );
+
+  let xp = game.objects[PLAYER].fighter.map_or(0, |(f, _)| f.xp);
+  render_bar(
+    &mut tcod.panel,
+    1,
+    2,
+    BAR_WIDTH,
+    "XP",
+    xp,
+    game.level_up_xp(),
+    LIGHT_PURPLE,
+    DARKER_VIOLET,
+  );
   render_mouselook(tcod, names_under_mouse);
+  render_levels(tcod, game);
+  render_statuses(tcod, game);
+  render_seed(tcod, game.seed);
   render_messages(tcod, game);
 
   blit(
@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::content::Content;
+use crate::game::Game;
+use crate::generator::Generator;
+
+static REPLAY_VERSION: u32 = 18;
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+  pub version: u32,
+  pub seed: u32,
+  pub generator: Generator,
+  // The same actions play out differently against different monsters, items
+  // and prefabs
+  pub content_hash: u64,
+  pub actions: Vec<Action>,
+  pub final_state: Option<u64>,
+}
+
+// FNV-1a over the serialized value, so the hash is stable between builds
+fn hash<T: Serialize>(value: &T) -> u64 {
+  let data = serde_json::to_string(value).expect("Game state and content should always serialize");
+
+  data.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+  })
+}
+
+pub fn state_hash(game: &Game) -> u64 {
+  hash(game)
+}
+
+pub fn content_hash(content: &Content) -> u64 {
+  hash(content)
+}
+
+impl Replay {
+  pub fn new(seed: u32, generator: Generator, content: &Content) -> Self {
+    Replay {
+      version: REPLAY_VERSION,
+      seed,
+      generator,
+      content_hash: content_hash(content),
+      actions: vec![],
+      final_state: None,
+    }
+  }
+
+  pub fn record(&mut self, action: Action) {
+    self.actions.push(action);
+  }
+
+  pub fn finish(&mut self, game: &Game) {
+    self.final_state = Some(state_hash(game));
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let data = serde_json::to_string(self)?;
+
+    let mut file = File::create(path)?;
+    file.write_all(data.as_bytes())?;
+
+    Ok(())
+  }
+
+  pub fn load(path: &str) -> Result<Replay, Box<dyn Error>> {
+    let mut data = String::new();
+    let mut file = File::open(path)?;
+    file.read_to_string(&mut data)?;
+
+    let replay = serde_json::from_str::<Replay>(&data)?;
+    if replay.version != REPLAY_VERSION {
+      return Err(format!(
+        "Replay file version {} is not supported (expected {})",
+        replay.version,
+        REPLAY_VERSION,
+      ).into());
+    }
+
+    Ok(replay)
+  }
+
+  pub fn check_content(&self, content: &Content) -> Result<(), String> {
+    let actual = content_hash(content);
+
+    if actual != self.content_hash {
+      return Err(format!(
+        "The game data {:016x} does not match the data the replay was recorded with {:016x}",
+        actual,
+        self.content_hash,
+      ));
+    }
+
+    Ok(())
+  }
+
+  pub fn check_final_state(&self, game: &Game) -> Result<(), String> {
+    let actual = state_hash(game);
+
+    match self.final_state {
+      Some(expected) if expected != actual => Err(format!(
+        "Final state {:016x} does not match the recorded state {:016x}",
+        actual,
+        expected,
+      )),
+      _ => Ok(()),
+    }
+  }
+}
+
+pub fn run_headless(replay: &Replay, content: Rc<Content>) -> Result<Game, String> {
+  replay.check_content(&content)?;
+
+  let mut game = Game::new(replay.seed, replay.generator, content)?;
+
+  for &action in &replay.actions {
+    game.perform(action)?;
+  }
+
+  replay.check_final_state(&game)?;
+
+  Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record(content: &Rc<Content>) -> Replay {
+    let mut game = Game::new(5, Generator::default(), content.clone()).unwrap();
+    let mut replay = Replay::new(game.seed, game.generator, content);
+
+    for &action in &[Action::Move(1, 0), Action::Move(0, 1), Action::Move(-1, 0), Action::Move(0, -1)] {
+      game.perform(action).unwrap();
+      replay.record(action);
+    }
+
+    replay.finish(&game);
+    replay
+  }
+
+  #[test]
+  fn replays_to_the_recorded_state() {
+    let content = Rc::new(Content::load().unwrap());
+
+    assert!(run_headless(&record(&content), content).is_ok());
+  }
+
+  #[test]
+  fn refuses_different_content() {
+    let content = Rc::new(Content::load().unwrap());
+    let replay = record(&content);
+
+    assert!(run_headless(&replay, Rc::new(Content::default())).is_err());
+  }
+}
@@ -0,0 +1,28 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// The same xorshift generator as rand's XorShiftRng, but with its state out in
+// the open so saves can carry on exactly where the game left off
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRng {
+  x: u32,
+  y: u32,
+  z: u32,
+  w: u32,
+}
+
+impl Rng for GameRng {
+  fn next_u32(&mut self) -> u32 {
+    let t = self.x ^ (self.x << 11);
+    self.x = self.y;
+    self.y = self.z;
+    self.z = self.w;
+    self.w = self.w ^ (self.w >> 19) ^ (t ^ (t >> 8));
+    self.w
+  }
+}
+
+// The last word is a constant so the state is never all zeroes
+pub fn seeded_rng(seed: u32) -> GameRng {
+  GameRng { x: seed, y: seed ^ 0x9e37_79b9, z: seed.rotate_left(16) ^ 0x85eb_ca6b, w: 0xc2b2_ae35 }
+}
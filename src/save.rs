@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::content::Content;
+use crate::game::Game;
+
+static SAVE_FILE: &str = "savegame";
+static SAVE_VERSION: u32 = 18;
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+  version: u32,
+  game: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+  version: u32,
+}
+
+#[derive(Deserialize)]
+struct LoadedSave {
+  game: Game,
+}
+
+pub fn save_exists() -> bool {
+  Path::new(SAVE_FILE).exists()
+}
+
+fn to_json(game: &Game) -> Result<String, Box<dyn Error>> {
+  Ok(serde_json::to_string(&SaveFile { version: SAVE_VERSION, game })?)
+}
+
+fn from_json(data: &str, content: Rc<Content>) -> Result<Game, Box<dyn Error>> {
+  let header = serde_json::from_str::<SaveHeader>(data)?;
+  if header.version != SAVE_VERSION {
+    return Err(format!(
+      "Save file version {} is not supported (expected {})",
+      header.version,
+      SAVE_VERSION,
+    ).into());
+  }
+
+  let mut game = serde_json::from_str::<LoadedSave>(data)?.game;
+  game.content = content;
+  game.init_fov();
+  game.compute_fov();
+
+  Ok(game)
+}
+
+pub fn save_game(game: &Game) -> Result<(), Box<dyn Error>> {
+  let data = to_json(game)?;
+
+  let mut file = File::create(SAVE_FILE)?;
+  file.write_all(data.as_bytes())?;
+
+  Ok(())
+}
+
+pub fn load_game(content: Rc<Content>) -> Result<Game, Box<dyn Error>> {
+  let mut data = String::new();
+  let mut file = File::open(SAVE_FILE)?;
+  file.read_to_string(&mut data)?;
+
+  from_json(&data, content)
+}
+
+pub fn delete_save() -> Result<(), Box<dyn Error>> {
+  if save_exists() {
+    fs::remove_file(SAVE_FILE)?;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::action::Action;
+  use crate::generator::Generator;
+  use crate::replay::state_hash;
+
+  static DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+  fn walk(game: &mut Game, steps: usize) {
+    for step in 0..steps {
+      let (dx, dy) = DIRECTIONS[step % DIRECTIONS.len()];
+      game.perform(Action::Move(dx, dy)).unwrap();
+    }
+  }
+
+  #[test]
+  fn loaded_game_continues_like_the_original() {
+    let content = Rc::new(Content::load().unwrap());
+    let mut game = Game::new(3, Generator::default(), content.clone()).unwrap();
+    walk(&mut game, 10);
+
+    let mut loaded = from_json(&to_json(&game).unwrap(), content).unwrap();
+    walk(&mut game, 10);
+    walk(&mut loaded, 10);
+
+    assert_eq!(state_hash(&game), state_hash(&loaded));
+  }
+}
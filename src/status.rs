@@ -0,0 +1,66 @@
+use std::mem::discriminant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+  Poison { damage: i32 },
+  Regeneration { amount: i32 },
+  Haste,
+  Slow,
+  Confusion,
+  Blindness,
+  Strength { bonus: i32 },
+}
+
+impl Effect {
+  // Effects of the same kind don't stack, whatever their strength
+  pub fn same_kind(&self, other: &Effect) -> bool {
+    discriminant(self) == discriminant(other)
+  }
+
+  pub fn describe(&self) -> &'static str {
+    match self {
+      Effect::Poison { .. } => "poisoned",
+      Effect::Regeneration { .. } => "regenerating",
+      Effect::Haste => "hasted",
+      Effect::Slow => "slowed",
+      Effect::Confusion => "confused",
+      Effect::Blindness => "blind",
+      Effect::Strength { .. } => "strengthened",
+    }
+  }
+
+  // Short label shown in the panel
+  pub fn tag(&self) -> &'static str {
+    match self {
+      Effect::Poison { .. } => "PSN",
+      Effect::Regeneration { .. } => "RGN",
+      Effect::Haste => "HST",
+      Effect::Slow => "SLW",
+      Effect::Confusion => "CNF",
+      Effect::Blindness => "BLD",
+      Effect::Strength { .. } => "STR",
+    }
+  }
+
+  pub fn color(&self) -> Color {
+    match self {
+      Effect::Poison { .. } => GREEN,
+      Effect::Regeneration { .. } => LIGHT_VIOLET,
+      Effect::Haste => LIGHT_YELLOW,
+      Effect::Slow => LIGHT_BLUE,
+      Effect::Confusion => LIGHT_GREEN,
+      Effect::Blindness => DARK_RED,
+      Effect::Strength { .. } => ORANGE,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+  pub effect: Effect,
+  pub turns: i32,
+}
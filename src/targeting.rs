@@ -0,0 +1,137 @@
+use std::cmp;
+
+use ::tcod::input::KeyCode;
+
+use roguelike::constants::*;
+use roguelike::game::Game;
+use roguelike::item::Targeting;
+
+use crate::render::{names_at, render_game, Cursor};
+use crate::tcod::Tcod;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+  Tile(i32, i32),
+  Monster(usize),
+}
+
+impl Target {
+  pub fn pos(&self, game: &Game) -> (i32, i32) {
+    match *self {
+      Target::Tile(x, y) => (x, y),
+      Target::Monster(id) => game.objects[id].pos(),
+    }
+  }
+}
+
+fn monster_at(game: &Game, x: i32, y: i32) -> Option<usize> {
+  game.objects
+    .iter()
+    .position(|obj| obj.pos() == (x, y) && obj.alive && obj.fighter.is_some() && obj.ai.is_some())
+}
+
+fn visible_monsters(game: &Game, max_range: Option<f32>) -> Vec<usize> {
+  let player = &game.objects[PLAYER];
+
+  let mut monsters: Vec<usize> = (0..game.objects.len())
+    .filter(|&id| {
+      let obj = &game.objects[id];
+
+      id != PLAYER
+        && monster_at(game, obj.x, obj.y) == Some(id)
+        && game.fov.is_in_fov(obj.x, obj.y)
+        && max_range.is_none_or(|range| player.distance_to(obj) <= range)
+    })
+    .collect();
+
+  monsters.sort_by(|&a, &b| {
+    player.distance_to(&game.objects[a]).partial_cmp(&player.distance_to(&game.objects[b])).unwrap()
+  });
+
+  monsters
+}
+
+fn is_valid(game: &Game, targeting: Targeting, max_range: Option<f32>, x: i32, y: i32) -> bool {
+  let in_fov = game.fov.is_in_fov(x, y);
+  let in_range = max_range.is_none_or(|range| game.objects[PLAYER].distance(x, y) <= range);
+
+  match targeting {
+    Targeting::Tile => in_fov && in_range,
+    Targeting::Monster => in_fov && in_range && monster_at(game, x, y).is_some(),
+  }
+}
+
+// Lets the player pick a visible tile with the mouse or arrow keys. Tab cycles
+// through visible monsters, Enter or a left-click confirms, and Escape or a
+// right-click cancels.
+pub fn select_target(
+  tcod: &mut Tcod,
+  game: &Game,
+  targeting: Targeting,
+  max_range: Option<f32>,
+  prompt: &str,
+) -> Option<Target> {
+  let monsters = visible_monsters(game, max_range);
+  let mut next_monster = 0;
+
+  let (mut x, mut y) = match (targeting, monsters.first()) {
+    (Targeting::Monster, Some(&id)) => {
+      next_monster = 1;
+      game.objects[id].pos()
+    }
+    _ => game.objects[PLAYER].pos(),
+  };
+
+  let mut mouse_pos = (tcod.mouse.cx, tcod.mouse.cy);
+  tcod.mouse.lbutton_pressed = false;
+  tcod.mouse.rbutton_pressed = false;
+
+  while !tcod.root.window_closed() {
+    let valid = is_valid(game, targeting, max_range, x, y);
+
+    let names = names_at(game, x, y);
+    let status = if names.is_empty() { prompt.to_string() } else { names };
+
+    render_game(tcod, game, status, Some(&Cursor { x, y, valid }));
+    tcod.poll_input();
+
+    if (tcod.mouse.cx, tcod.mouse.cy) != mouse_pos {
+      mouse_pos = (tcod.mouse.cx, tcod.mouse.cy);
+      x = cmp::min(tcod.mouse.cx as i32, MAP_WIDTH - 1);
+      y = cmp::min(tcod.mouse.cy as i32, MAP_HEIGHT - 1);
+    }
+
+    let (dx, dy) = match tcod.key.code {
+      KeyCode::Up => (0, -1),
+      KeyCode::Down => (0, 1),
+      KeyCode::Left => (-1, 0),
+      KeyCode::Right => (1, 0),
+      _ => (0, 0),
+    };
+
+    x = cmp::max(0, cmp::min(x + dx, MAP_WIDTH - 1));
+    y = cmp::max(0, cmp::min(y + dy, MAP_HEIGHT - 1));
+
+    if tcod.key.code == KeyCode::Tab && !monsters.is_empty() {
+      let (monster_x, monster_y) = game.objects[monsters[next_monster % monsters.len()]].pos();
+      x = monster_x;
+      y = monster_y;
+      next_monster += 1;
+    }
+
+    let confirmed = tcod.key.code == KeyCode::Enter || tcod.mouse.lbutton_pressed;
+
+    if confirmed && is_valid(game, targeting, max_range, x, y) {
+      return Some(match monster_at(game, x, y) {
+        Some(id) => Target::Monster(id),
+        None => Target::Tile(x, y),
+      });
+    }
+
+    if tcod.key.code == KeyCode::Escape || tcod.mouse.rbutton_pressed {
+      return None;
+    }
+  }
+
+  None
+}
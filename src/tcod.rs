@@ -1,12 +1,37 @@
 use ::tcod::console::*;
-use ::tcod::input::{Key, Mouse};
-use ::tcod::map::{Map as FovMap};
+use ::tcod::input::{self, Event, Key, Mouse};
+
+use roguelike::color::Color;
+
+use crate::render::Overlay;
 
 pub struct Tcod {
   pub root: Root,
   pub con: Offscreen,
   pub panel: Offscreen,
-  pub fov: FovMap,
   pub key: Key,
   pub mouse: Mouse,
+  pub overlay: Option<Overlay>,
+}
+
+impl Tcod {
+  pub fn poll_input(&mut self) {
+    match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
+      Some((_, Event::Mouse(m))) => self.mouse = m,
+      Some((_, Event::Key(k))) => self.key = k,
+      _ => self.key = Default::default(),
+    }
+  }
+}
+
+// The core's colours live in the library, so they're converted through a
+// local trait rather than a From impl
+pub trait ToTcod {
+  fn to_tcod(self) -> ::tcod::colors::Color;
+}
+
+impl ToTcod for Color {
+  fn to_tcod(self) -> ::tcod::colors::Color {
+    ::tcod::colors::Color { r: self.r, g: self.g, b: self.b }
+  }
 }
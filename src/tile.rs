@@ -1,16 +1,164 @@
-#[derive(Clone, Copy, Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::constants::*;
+
+static COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
+static COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
+static COLOR_DARK_GROUND: Color = Color { r: 50, g: 50, b: 150 };
+static COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50 };
+static COLOR_DARK_WATER: Color = Color { r: 10, g: 20, b: 90 };
+static COLOR_LIGHT_WATER: Color = Color { r: 30, g: 70, b: 190 };
+static COLOR_DARK_LAVA: Color = Color { r: 80, g: 20, b: 50 };
+static COLOR_LIGHT_LAVA: Color = Color { r: 210, g: 60, b: 10 };
+static COLOR_DARK_GRASS: Color = Color { r: 30, g: 60, b: 110 };
+static COLOR_LIGHT_GRASS: Color = Color { r: 80, g: 140, b: 40 };
+
+static COLOR_DOOR: Color = Color { r: 160, g: 100, b: 40 };
+static COLOR_LOCKED_DOOR: Color = Color { r: 255, g: 191, b: 0 };
+static COLOR_WAVES: Color = Color { r: 120, g: 170, b: 255 };
+static COLOR_EMBERS: Color = Color { r: 255, g: 220, b: 60 };
+static COLOR_RUBBLE: Color = Color { r: 110, g: 100, b: 80 };
+static COLOR_GRASS: Color = Color { r: 150, g: 230, b: 90 };
+static COLOR_TREE: Color = Color { r: 20, g: 80, b: 20 };
+
+// Added to the cost of pathing through a tile that hurts, so monsters only
+// cross one when there's no other way round
+static HAZARD_PATH_COST: u32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Door {
+  Open,
+  Closed,
+  Locked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TileKind {
+  Floor,
+  Wall,
+  Door(Door),
+  Water,
+  Lava,
+  Rubble,
+  Grass,
+  Tree,
+}
+
+impl TileKind {
+  pub fn blocks(&self) -> bool {
+    matches!(
+      self,
+      TileKind::Wall | TileKind::Water | TileKind::Tree | TileKind::Door(Door::Closed) | TileKind::Door(Door::Locked)
+    )
+  }
+
+  // Projectiles fly over water, but are stopped by anything else that blocks
+  pub fn blocks_projectiles(&self) -> bool {
+    self.blocks() && *self != TileKind::Water
+  }
+
+  pub fn blocks_sight(&self) -> bool {
+    matches!(
+      self,
+      TileKind::Wall | TileKind::Grass | TileKind::Tree | TileKind::Door(Door::Closed) | TileKind::Door(Door::Locked)
+    )
+  }
+
+  // How many turns it takes to step onto the tile
+  pub fn move_cost(&self) -> u32 {
+    match self {
+      TileKind::Rubble => 2,
+      _ => 1,
+    }
+  }
+
+  // What stepping onto the tile costs a monster planning a path
+  pub fn path_cost(&self) -> u32 {
+    if self.damage() > 0 { self.move_cost() + HAZARD_PATH_COST } else { self.move_cost() }
+  }
+
+  // Damage dealt to anything that steps onto the tile
+  pub fn damage(&self) -> i32 {
+    match self {
+      TileKind::Lava => LAVA_DAMAGE,
+      _ => 0,
+    }
+  }
+
+  pub fn glyph(&self) -> Option<(char, Color)> {
+    match self {
+      TileKind::Door(Door::Open) => Some(('\'', COLOR_DOOR)),
+      TileKind::Door(Door::Closed) => Some(('+', COLOR_DOOR)),
+      TileKind::Door(Door::Locked) => Some(('+', COLOR_LOCKED_DOOR)),
+      TileKind::Water => Some(('~', COLOR_WAVES)),
+      TileKind::Lava => Some(('~', COLOR_EMBERS)),
+      TileKind::Rubble => Some((':', COLOR_RUBBLE)),
+      TileKind::Grass => Some(('"', COLOR_GRASS)),
+      TileKind::Tree => Some(('&', COLOR_TREE)),
+      _ => None,
+    }
+  }
+
+  pub fn background(&self, visible: bool) -> Color {
+    let (dark, light) = match self {
+      TileKind::Water => (COLOR_DARK_WATER, COLOR_LIGHT_WATER),
+      TileKind::Lava => (COLOR_DARK_LAVA, COLOR_LIGHT_LAVA),
+      TileKind::Grass | TileKind::Tree => (COLOR_DARK_GRASS, COLOR_LIGHT_GRASS),
+      kind if kind.blocks_sight() => (COLOR_DARK_WALL, COLOR_LIGHT_WALL),
+      _ => (COLOR_DARK_GROUND, COLOR_LIGHT_GROUND),
+    };
+
+    if visible { light } else { dark }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Tile {
-  pub blocked: bool,
-  pub block_sight: bool,
+  pub kind: TileKind,
   pub explored: bool,
 }
 
 impl Tile {
+  pub fn new(kind: TileKind) -> Self {
+    Tile { kind, explored: false }
+  }
+
   pub fn empty() -> Self {
-    Tile { blocked: false, explored: false, block_sight: false }
+    Tile::new(TileKind::Floor)
   }
 
   pub fn wall() -> Self {
-    Tile { blocked: true, explored: false, block_sight: true }
+    Tile::new(TileKind::Wall)
+  }
+
+  pub fn blocked(&self) -> bool {
+    self.kind.blocks()
+  }
+
+  pub fn block_sight(&self) -> bool {
+    self.kind.blocks_sight()
+  }
+
+  pub fn door(&self) -> Option<Door> {
+    match self.kind {
+      TileKind::Door(door) => Some(door),
+      _ => None,
+    }
+  }
+
+  pub fn set_door(&mut self, door: Door) {
+    self.kind = TileKind::Door(door);
+  }
+
+  // Whether a monster can walk through, opening a door on the way if it has
+  // to. Tiles that hurt are walkable, but paths avoid them where they can.
+  pub fn is_walkable(&self) -> bool {
+    !self.blocked() || self.door() == Some(Door::Closed)
+  }
+
+  // Walkable without getting hurt
+  pub fn is_safe(&self) -> bool {
+    self.is_walkable() && self.kind.damage() == 0
   }
 }